@@ -1,708 +1,6590 @@
-    #![feature(exact_size_is_empty)]
-    #![feature(random)]
-    #![feature(dec2flt)]
-    extern crate core;
-
-    use std::random::random;
-    use std::time::{Duration, SystemTime};
+    use std::any::TypeId;
+    use std::cmp::Reverse;
+    use std::collections::{HashMap, VecDeque};
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant, SystemTime};
+    use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, LoadState, RecursiveDependencyLoadState};
+    use bevy::asset::io::Reader;
     use bevy::color::palettes::css::{CRIMSON, WHITE};
+    use bevy::ecs::entity::{EntityHashMap, EntityMapper, MapEntities};
+    use bevy::ecs::reflect::{ReflectMapEntities, ReflectMapEntitiesResource};
+    use bevy::ecs::schedule::ScheduleLabel;
+    use bevy::ecs::system::{RunSystemOnce, SystemParam};
+    use bevy::input::{ButtonState, InputSystem};
+    use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+    use bevy::input::keyboard::{Key, KeyboardInput};
     use bevy::math::NormedVectorSpace;
     use bevy::prelude::*;
-    use rand::Rng;
-    use bevy_framepace::*;  
+    use bevy::reflect::TypeRegistry;
+    use bevy::render::camera::{ScalingMode, Viewport};
+    use bevy::render::view::NoFrustumCulling;
+    use bevy::scene::DynamicEntity;
+    use bevy::scene::serde::SceneDeserializer;
+    use bevy::window::PrimaryWindow;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use serde::{Deserialize, Serialize};
+    use serde::de::DeserializeSeed;
+
+    // the screen the game is drawn for in art pixels, scaled up by a whole number to fit the window
+    const VIRTUAL_RESOLUTION: UVec2 = UVec2::new(384, 216);
+    // world units per art pixel, the scale every sprite is spawned with
+    const PIXEL_SCALE: f32 = 3.0;
+    // the narrow playfield of the portrait layout in art pixels, the HUD fills the sides
+    const PORTRAIT_PLAYFIELD: UVec2 = UVec2::new(162, 216);
+    // shown behind the playfield, the letterbox bars around it are black
+    const PLAYFIELD_BACKGROUND: Color = Color::srgb(0.169, 0.173, 0.184);
+    const HUD_PANEL_COLOR: Color = Color::srgb(0.08, 0.08, 0.1);
+    // how far past the playfield's edge projectiles, enemies and pickups may go before despawning
+    const PLAYFIELD_CULL_MARGIN: f32 = 200.0;
+    // keeps the whole ship sprite inside the playfield
+    const PLAYER_EDGE_INSET: Vec2 = Vec2::new(24.0, 36.0);
+
+    // gameplay ticks per second, rendering interpolates between them
+    const DEFAULT_TICK_RATE: f64 = 60.0;
+    // what a tick rate from the save file is held to, slower lets fast shots skip past enemies
+    const MIN_TICK_RATE: f64 = 30.0;
+    const MAX_TICK_RATE: f64 = 240.0;
+
+    // ship.png columns from hard left to hard right, each clip loops that column's thruster frames
+    const SHIP_BANK_CLIPS: [&str; 5] = ["bank-hard-left", "bank-left", "idle", "bank-right", "bank-hard-right"];
+    // how quickly the ship leans into and out of a bank, higher is snappier
+    const SHIP_BANK_EASING: f32 = 8.0;
+
+    const STARTING_LIVES: u32 = 3;
+    // how far either side of the centre the two ships of a co-op game start
+    const PLAYER_SPAWN_SPACING: f32 = 80.0;
+    const STARTING_BOMBS: u32 = 3;
+    const MAX_BOMBS: u32 = 5;
+    // enough to destroy any enemy on screen
+    const BOMB_DAMAGE: u32 = 10;
+    const BOMB_INVULNERABILITY_SECONDS: f32 = 1.5;
+    const ENEMY_MOVEMENT_SPEED: f32 = 250.0;
+    const ENEMY_BULLET_SPEED: f32 = 300.0;
+    // fixed ticks between enemy spawns on normal difficulty
+    const ENEMY_SPAWN_INTERVAL: f32 = 30.0;
+    // once the listed waves run out, each repeat of the last one is this much harder, up to a cap
+    const DIFFICULTY_RAMP_PER_WAVE: f32 = 0.08;
+    const DIFFICULTY_RAMP_MAX_WAVES: u32 = 10;
+    // endless mode sends a mini-boss at the start of every this many tiers
+    const MINI_BOSS_EVERY_TIERS: u32 = 3;
+    const MINI_BOSS_HEALTH: u32 = 20;
+    const MINI_BOSS_FIRE_INTERVAL: f32 = 0.6;
+    const MINI_BOSS_SCALE: f32 = 5.0;
+    const MINI_BOSS_BONUS: u64 = 2500;
+    // enemies are spawned this far above the top of the playfield, formations reach up to
+    // about another 80 pixels, well within the cull margin
+    const ENEMY_SPAWN_HEIGHT: f32 = 60.0;
+    // keeps randomly placed spawns and strafing enemies this far from the playfield's sides
+    const ENEMY_EDGE_INSET: f32 = 64.0;
+    // formation members catch up with their slot this much faster than the formation flies
+    const FORMATION_CATCH_UP: f32 = 2.0;
+    // circle formations turn this many radians per second
+    const FORMATION_CIRCLE_SPIN: f32 = 1.0;
+    // per member, for destroying every enemy of a formation
+    const FORMATION_CLEAR_BONUS: u64 = 200;
+    // straight pieces each Bézier segment of an enemy path is approximated with
+    const PATH_SAMPLES_PER_SEGMENT: usize = 16;
+    const RESPAWN_INVULNERABILITY_SECONDS: f32 = 2.0;
+    const WAVE_DURATION_SECONDS: f32 = 30.0;
+
+    // every this many kills in a row the chain multiplier goes up by one
+    const CHAIN_KILLS_PER_STEP: u32 = 4;
+    const CHAIN_MAX_MULTIPLIER: u32 = 8;
+    // without a kill for this long the multiplier drops a step
+    const CHAIN_DECAY_SECONDS: f32 = 2.0;
+    const NO_DAMAGE_WAVE_BONUS: u64 = 5000;
+    // awarded in full for 100% accuracy over a wave, scaled down below that
+    const ACCURACY_BONUS: u64 = 5000;
+    const ACCURACY_BONUS_MIN_SHOTS: u32 = 10;
+    const SCORE_POPUP_SECONDS: f32 = 0.8;
+    const SCORE_POPUP_RISE_SPEED: f32 = 60.0;
+
+    // chance a destroyed enemy drops a pickup
+    const POWER_UP_DROP_CHANCE: f64 = 0.08;
+    const POWER_UP_FALL_SPEED: f32 = 120.0;
+    // one pickup in this many is a bomb instead of a weapon, and one of the rest a shield
+    const BOMB_POWER_UP_ODDS: u32 = 4;
+    const SHIELD_POWER_UP_ODDS: u32 = 5;
+    const SHIELD_SECONDS: f32 = 8.0;
+
+    // how fast camera trauma wears off per second, the shake follows its square
+    const TRAUMA_DECAY: f32 = 1.5;
+    const MAX_SHAKE_OFFSET: f32 = 24.0;
+    const MAX_SHAKE_ANGLE: f32 = 0.03;
+    const SHAKE_FREQUENCY: f32 = 30.0;
+    // how fast the game runs during a hit-stop
+    const HIT_STOP_SPEED: f32 = 0.05;
+    // opacity lost per second by the bomb flash
+    const FLASH_FADE: f32 = 2.5;
 
-    const BOUNDS: Vec2 = Vec2::new(1200.0, 640.0);
+    const NET_DEFAULT_ADDRESS: &str = "127.0.0.1:7777";
+    const NET_DEFAULT_PORT: u16 = 7777;
+    // ticks a player's input waits before it's used online, time for it to reach the other side
+    const NET_INPUT_DELAY: u32 = 2;
+    // ticks a networked game may run ahead of the other player's inputs before it waits for them
+    const NET_MAX_PREDICTION: u32 = 8;
+    const NET_MAX_INPUTS_PER_PACKET: usize = 32;
+    // settled ticks whose checksums are kept to compare with the other player's, which arrive late
+    const NET_CHECKSUM_HISTORY: usize = 256;
+    const NET_MAX_PACKET_SIZE: usize = 4096;
+    const NET_HELLO_INTERVAL_SECONDS: f32 = 0.5;
+    const NET_TIMEOUT_SECONDS: f32 = 5.0;
+    const NET_ADDRESS_LENGTH: usize = 64;
+
+    const SAVE_FILE_NAME: &str = "save.ron";
+    // written and read with F5 and F9, for debugging
+    const STATE_FILE_NAME: &str = "state.ron";
+    const CHECKPOINT_FILE_NAME: &str = "checkpoint.ron";
+    const HIGH_SCORE_TABLE_SIZE: usize = 10;
+    const HIGH_SCORE_NAME_LENGTH: usize = 3;
+    // how long a lost campaign run waits for the player to continue it
+    const CONTINUE_SECONDS: f32 = 10.0;
 
     fn main() {
         App::new()
-            .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))// prevents blurry sprites
-            .add_plugins(bevy_framepace::FramepacePlugin)
+            .add_plugins(DefaultPlugins
+                .set(ImagePlugin::default_nearest())// prevents blurry sprites
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        resolution: (VIRTUAL_RESOLUTION.as_vec2() * PIXEL_SCALE).into(),
+                        ..default()
+                    }),
+                    ..default()
+                }))
+            .insert_resource(ClearColor(Color::BLACK))
+            .init_asset::<SpriteManifest>()
+            .init_asset_loader::<SpriteManifestLoader>()
+            .init_asset::<WeaponManifest>()
+            .init_asset_loader::<WeaponManifestLoader>()
+            .init_asset::<WaveManifest>()
+            .init_asset_loader::<WaveManifestLoader>()
             .init_state::<GameState>()
             .init_state::<MenuState>()
+            .init_resource::<GameMode>()
+            .init_resource::<Difficulty>()
             .add_systems(Startup, setup_camera)
+            .init_resource::<Playfield>()
+            .init_resource::<DisplaySettings>()
+            .add_systems(Update, apply_display_settings.run_if(resource_changed::<DisplaySettings>))
+            .add_systems(Update, fit_camera_to_window)
+            .add_systems(Startup, load_save_data)
             .add_systems(OnEnter(GameState::Over), menu_setup)
-            .add_systems(OnEnter(GameState::Over), main_menu_setup)
+            // once the run's name entry is done with its mode and difficulty
+            .add_systems(OnEnter(GameState::Over), restore_own_settings.run_if(resource_exists::<OwnSettings>))
+            .add_systems(OnEnter(MenuState::Main), main_menu_setup)
+            .add_systems(OnExit(MenuState::Main), despawn_screen::<OnMainMenuScreen>)
+            .add_systems(OnEnter(MenuState::HighScores), high_scores_setup)
+            .add_systems(OnExit(MenuState::HighScores), despawn_screen::<OnHighScoresScreen>)
+            .add_systems(OnEnter(MenuState::Settings), settings_menu_setup)
+            .add_systems(OnExit(MenuState::Settings), despawn_screen::<OnSettingsMenuScreen>)
+            .add_systems(OnEnter(MenuState::SettingsDisplay), display_settings_menu_setup)
+            .add_systems(OnExit(MenuState::SettingsDisplay), despawn_screen::<OnDisplaySettingsMenuScreen>)
             .add_systems(Update, (menu_action, button_system).run_if(in_state(GameState::Over)))
+            .add_systems(Update, (
+                option_button_system::<Layout>,
+                option_button_system::<ParticleDensity>,
+                option_button_system::<EffectIntensity>,
+            ).run_if(in_state(MenuState::SettingsDisplay)))
+            .add_systems(OnEnter(MenuState::NewGame), new_game_menu_setup)
+            .add_systems(OnExit(MenuState::NewGame), despawn_screen::<OnNewGameScreen>)
+            .add_systems(Update, (
+                option_button_system::<GameMode>,
+                option_button_system::<Difficulty>,
+                option_button_system::<PlayerCount>,
+                option_button_system::<LifeSharing>,
+            ).run_if(in_state(MenuState::NewGame)))
+            .add_systems(OnEnter(MenuState::Online), online_menu_setup)
+            .add_systems(OnExit(MenuState::Online), (despawn_screen::<OnOnlineScreen>, close_net_lobby))
+            .add_systems(Update, (
+                option_button_system::<SimulatedLag>,
+                option_button_system::<SimulatedLoss>,
+                join_address_system,
+            ).run_if(in_state(MenuState::Online)))
+            .add_systems(Update, net_lobby_system.run_if(resource_exists::<NetLobby>))
+            .add_systems(Update, net_linger_system.run_if(resource_exists::<NetSession>.and_then(not(in_state(GameState::Game)))))
+            .add_systems(OnEnter(GameState::NameEntry), name_entry_setup)
+            .add_systems(Update, name_entry_system.run_if(in_state(GameState::NameEntry)))
+            .add_systems(OnExit(GameState::NameEntry), despawn_screen::<OnNameEntryScreen>)
             .add_systems(OnEnter(GameState::Game), setup)
+            .add_systems(OnEnter(GameState::Game), hud_setup)
+            .add_systems(OnEnter(GameState::Game), resume_checkpoint.after(setup).run_if(resource_exists::<Checkpoint>))
+            .add_systems(Update, checkpoint_system.run_if(in_state(GameState::Game)
+                .and_then(resource_equals(GameMode::Campaign))
+                .and_then(not(resource_exists::<NetSession>))))
+            .add_systems(Update, continue_setup.run_if(resource_added::<ContinueCountdown>))
+            .add_systems(Update, continue_system.run_if(resource_exists::<ContinueCountdown>))
+            .add_systems(Update, (
+                hud_score_system,
+                hud_lives_system,
+                hud_player_score_system,
+                hud_bombs_system,
+                hud_multiplier_system,
+                hud_wave_system,
+                hud_power_ups_system,
+                hud_weapon_system,
+                hud_survival_system,
+            ).run_if(in_state(GameState::Game)))
+            .add_systems(Update, (invulnerability_blink_system, homing_rotation_system))
+            .init_resource::<BombRequested>()
+            .init_resource::<GamepadAssignments>()
+            .add_systems(PreUpdate, gamepad_assignment_system.after(InputSystem))
+            .add_systems(Update, bomb_input_system.run_if(in_state(GameState::Game)))
+            .add_systems(Update, (score_popup_spawn_system, score_popup_system).chain().run_if(resource_exists::<GameAssets>))
             .add_systems(OnExit(GameState::Game), clear_after_game_over)
-        .add_systems(OnExit(GameState::Over), despawn_screen::<OnMainMenuScreen>)
+            .add_systems(OnExit(GameState::Game), despawn_screen::<OnGameScreen>)
+            .add_systems(OnEnter(GameState::Loading), loading_setup)
+            .add_systems(Update, loading_progress_system.run_if(in_state(GameState::Loading)))
+            .add_systems(OnExit(GameState::Loading), despawn_screen::<OnLoadingScreen>)
             .add_systems(Startup, splash_setup)
             .add_systems(Update, countdown.after(splash_setup))
+            .init_resource::<HitStop>()
+            .add_systems(Update, (impact_system, camera_shake_system, screen_flash_system).chain())
+            .add_systems(Update, (
+                particle_burst_system,
+                particle_emitter_system,
+                particle_system,
+            ).chain().after(interpolate_rendered_transform))
+            .init_resource::<SimulationSettings>()
+            .add_systems(Startup, apply_simulation_settings)
+            .add_systems(Update, apply_simulation_settings.run_if(resource_changed::<SimulationSettings>))
             .add_systems(Update, execute_animations.after(setup))
-            .add_systems(Update, player_movement_system.after(setup))
-            .add_systems(Update, player_weapons_system.after(setup))
-            .add_systems(Update, player_shoot_system.after(setup))
-            .add_systems(Update, enemy_movement_system.after(setup))
-            .add_systems(Update, enemy_kill_system.after(setup))
-            .add_systems(Update, explosion_and_laser_termination_system.after(setup))
-            .add_systems(Update, player_kill_system.after(setup))
-            .add_systems(Update, enemy_spawn_system.after(setup))
-            .add_systems(Update, spawn_timer_system.after(setup))
+            .add_event::<AnimationFinished>()
+            .add_systems(Update, apply_sprite_clips.before(execute_animations).run_if(resource_exists::<GameAssets>))
+            .add_systems(Update, despawn_finished_explosions.after(execute_animations))
+            .add_systems(Update, interpolate_rendered_transform)
+            // the whole simulation runs on the fixed clock in a fixed order so a tick is reproducible,
+            // online it's run by the session, which may roll back and run ticks again
+            .add_plugins(simulation_plugin)
+            .add_plugins(debug_keys_plugin)
+            .add_systems(FixedUpdate, (
+                // held while a lost run counts down to giving up
+                (local_input_system, run_simulation).chain().run_if(not(resource_exists::<NetSession>).and_then(not(resource_exists::<ContinueCountdown>))),
+                net_simulation_system.run_if(resource_exists::<NetSession>),
+            ).run_if(in_state(GameState::Game)))
         .run();
     }
 
-    fn trigger_animation<S: Component>(mut query: Query<&mut AnimationConfig, With<S>>) {
-        let mut animation = query.single_mut();
-        animation.frame_timer = AnimationConfig::timer_from_fps(animation.fps);
+    /// The gameplay tick and what it needs besides the loaded manifests and the run's settings.
+    fn simulation_plugin(app: &mut App) {
+        app
+            .add_event::<ScoreEvent>()
+            .add_event::<PointsScored>()
+            .add_event::<EnemyDestroyed>()
+            .add_event::<ParticleBurst>()
+            .add_event::<Impact>()
+            .init_resource::<TickInput>()
+            // everything a `Snapshot` copies, see `SnapshotFilter`
+            .register_type::<PhysicalTranslation>()
+            .register_type::<PreviousPhysicalTranslation>()
+            .register_type::<PlayerId>()
+            .register_type::<Player>()
+            .register_type::<Cooldown>()
+            .register_type::<Weapon>()
+            .register_type::<Invulnerable>()
+            .register_type::<Enemy>()
+            .register_type::<EnemyMovement>()
+            .register_type::<Formation>()
+            .register_type::<FormationMember>()
+            .register_type::<MiniBoss>()
+            .register_type::<Laser>()
+            .register_type::<EnemyBullet>()
+            .register_type::<PowerUp>()
+            .register_type::<SpawnTimer>()
+            .register_type::<GameRng>()
+            .register_type::<RunInfo>()
+            .register_type::<Score>()
+            .register_type::<Pilots>()
+            .register_type::<Chain>()
+            .register_type::<WaveStats>()
+            .register_type::<Wave>()
+            .register_type::<Challenge>()
+            .register_type::<Survival>()
+            .register_type::<MiniBosses>()
+            .register_type::<ActivePowerUps>()
+            .init_resource::<SnapshotFilter>()
+            .add_systems(Simulation, (
+                (
+                    save_previous_physical_translation,
+                    survival_timer_system,
+                    wave_system,
+                    mini_boss_spawn_system,
+                    chain_decay_system,
+                    power_up_timer_system,
+                    invulnerability_system,
+                    spawn_timer_system,
+                    enemy_spawn_system,
+                ).chain(),
+                (
+                    player_movement_system,
+                    player_shoot_system,
+                    bomb_system,
+                    player_weapons_system,
+                ).chain(),
+                (
+                    enemy_movement_system,
+                    formation_movement_system,
+                    enemy_fire_system,
+                    enemy_bullet_system,
+                ).chain(),
+                (
+                    enemy_kill_system,
+                    enemy_debris_system,
+                    formation_status_system,
+                    mini_boss_status_system,
+                    power_up_movement_system,
+                    power_up_collect_system,
+                    player_kill_system,
+                    offscreen_culling_system,
+                    score_system,
+                    run_over_system,
+                ).chain(),
+            ).chain());
+    }
+
+    #[derive(Deserialize, Clone, Copy, Default, Eq, PartialEq, Debug)]
+    enum PlaybackMode {
+        #[default]
+        Loop,
+        Once,
+        PingPong,
+    }
+
+    /// Sent when a `PlaybackMode::Once` animation has shown its last frame for its full duration.
+    #[derive(Event)]
+    struct AnimationFinished {
+        entity: Entity,
     }
 
     #[derive(Component)]
     struct AnimationConfig {
-        first_sprite_index: usize,
-        last_sprite_index: usize,
+        frames: Vec<usize>,
+        // seconds per frame, frames without an entry last 1 / fps
+        frame_durations: Vec<f32>,
         fps: u8,
+        mode: PlaybackMode,
+        current_frame: usize,
+        reversing: bool,
+        finished: bool,
         frame_timer: Timer,
     }
 
     impl AnimationConfig {
-        fn new(first: usize, last: usize, fps: u8) -> Self {
-            Self {
-                first_sprite_index: first,
-                last_sprite_index: last,
+        fn new(frames: Vec<usize>, frame_durations: Vec<f32>, fps: u8, mode: PlaybackMode) -> Self {
+            let mut config = Self {
+                frames,
+                frame_durations,
                 fps,
-                frame_timer: Self::timer_from_fps(fps),
-            }
+                mode,
+                current_frame: 0,
+                reversing: false,
+                finished: false,
+                frame_timer: Timer::default(),
+            };
+            config.restart();
+            config
         }
 
         fn timer_from_fps(fps: u8) -> Timer {
             Timer::new(Duration::from_secs_f32(1.0 / (fps as f32)), TimerMode::Once)
         }
+
+        fn sprite_index(&self) -> usize {
+            self.frames[self.current_frame]
+        }
+
+        fn restart(&mut self) {
+            self.current_frame = 0;
+            self.reversing = false;
+            self.finished = false;
+            self.frame_timer = self.current_frame_timer();
+        }
+
+        fn current_frame_timer(&self) -> Timer {
+            match self.frame_durations.get(self.current_frame) {
+                Some(seconds) => Timer::from_seconds(*seconds, TimerMode::Once),
+                None => Self::timer_from_fps(self.fps),
+            }
+        }
+
+        /// Moves to the next frame according to the playback mode. Returns false instead once a
+        /// one-shot animation has run out of frames.
+        fn advance(&mut self) -> bool {
+            let last = self.frames.len() - 1;
+
+            match self.mode {
+                PlaybackMode::Loop => {
+                    self.current_frame = if self.current_frame >= last { 0 } else { self.current_frame + 1 };
+                }
+                PlaybackMode::Once => {
+                    if self.current_frame >= last {
+                        return false;
+                    }
+                    self.current_frame += 1;
+                }
+                PlaybackMode::PingPong => {
+                    if last == 0 {
+                        return true;
+                    }
+                    if self.current_frame >= last {
+                        self.reversing = true;
+                    } else if self.current_frame == 0 {
+                        self.reversing = false;
+                    }
+                    self.current_frame = if self.reversing { self.current_frame - 1 } else { self.current_frame + 1 };
+                }
+            }
+
+            self.frame_timer = self.current_frame_timer();
+            true
+        }
     }
 
     fn execute_animations(
         time: Res<Time>,
-        mut query: Query<(&mut AnimationConfig, &mut TextureAtlas)>,
+        mut finished_events: EventWriter<AnimationFinished>,
+        mut query: Query<(Entity, &mut AnimationConfig, &mut TextureAtlas)>,
     ) {
-        for (mut config, mut atlas) in &mut query {
+        for (entity, mut config, mut atlas) in &mut query {
+            if config.finished {
+                continue;
+            }
+
             config.frame_timer.tick(time.delta());
 
             if config.frame_timer.just_finished() {
-                atlas.index += 1;
-
-                if atlas.index > config.last_sprite_index {
-                    atlas.index = config.first_sprite_index;
+                if config.advance() {
+                    atlas.index = config.sprite_index();
+                } else {
+                    config.finished = true;
+                    finished_events.send(AnimationFinished { entity });
                 }
-
-                config.frame_timer = AnimationConfig::timer_from_fps(config.fps);
             }
         }
     }
-    #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
-    enum MenuState {
-        Main,
-        Settings,
-        SettingsDisplay,
-        SettingsSound,
-        #[default]
-        Disabled,
-    }
-
-    #[derive(Component)]
-    struct OnMainMenuScreen;
-
-    #[derive(Component)]
-    struct OnSettingsMenuScreen;
-
-    #[derive(Component)]
-    struct OnDisplaySettingsMenuScreen;
-
-    #[derive(Component)]
-    struct OnSoundSettingsMenuScreen;
-
-
-    const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
-    const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
-    const HOVERED_PRESSED_BUTTON: Color = Color::srgb(0.25, 0.65, 0.25);
-    const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
-
-    #[derive(Component)]
-    struct SelectedOption;
 
-    #[derive(Component)]
-    enum MenuButtonAction {
-        Play,
-        Settings,
-        SettingsDisplay,
-        SettingsSound,
-        BackToMainMenu,
-        BackToSettings,
-        Quit,
+    /// A named animation of a spritesheet, as written in the sprite manifest.
+    #[derive(Deserialize, Clone)]
+    struct AnimationClip {
+        frames: Vec<usize>,
+        fps: u8,
+        #[serde(default)]
+        durations: Vec<f32>,
+        #[serde(default)]
+        mode: PlaybackMode,
     }
 
-
-    #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
-    enum GameState {
-        #[default]
-        Splash,
-        Menu,
-        Game,
-        Over
+    impl AnimationClip {
+        fn to_config(&self) -> AnimationConfig {
+            AnimationConfig::new(self.frames.clone(), self.durations.clone(), self.fps, self.mode)
+        }
     }
 
-    #[derive(Component)]
-    struct ScoreCounter {
-        score: f32,
+    #[derive(Deserialize)]
+    struct SpriteSheetDescriptor {
+        texture: String,
+        cell_size: (u32, u32),
+        columns: u32,
+        rows: u32,
+        clips: HashMap<String, AnimationClip>,
     }
 
-    #[derive(Component)]
-    struct ScoreCounterText;
-
-    #[derive(Component)]
-    struct SpawnTimer {
-        timer: f32
+    #[derive(Deserialize)]
+    struct SpriteManifestDescriptor {
+        sheets: HashMap<String, SpriteSheetDescriptor>,
     }
 
-    #[derive(Component)]
-    struct Explosion {
-        frame_timer: f32
+    struct SpriteSheet {
+        texture: Handle<Image>,
+        layout: Handle<TextureAtlasLayout>,
+        clips: HashMap<String, AnimationClip>,
     }
 
-    #[derive(Component)]
-    struct Enemy {
-        is_hit: bool,
-        position: Vec3,
-        movement_speed: f32
-    }
+    impl SpriteSheet {
+        fn clip(&self, name: &str) -> Option<&AnimationClip> {
+            self.clips.get(name)
+        }
 
-    #[derive(Component)]
-    struct Cooldown {
-        last_time: u128
+        // the animation of a clip the loader made sure is there
+        fn config(&self, clip: &str) -> AnimationConfig {
+            self.clip(clip)
+                .map(AnimationClip::to_config)
+                .expect("the sprite manifest loader checks every clip the game plays is there")
+        }
     }
 
-    #[derive(Component)]
-    struct LaserSprite;
+    /// Every spritesheet the game draws, loaded from a `.sprites.ron` file. Textures are loaded
+    /// as dependencies and atlas layouts are labeled sub-assets, so a hot reload rebuilds both.
+    #[derive(Asset, TypePath)]
+    struct SpriteManifest {
+        sheets: HashMap<String, SpriteSheet>,
+    }
 
-    #[derive(Component)]
-    struct Laser {
-        movement_speed: f32,
-        laser_sprite: LaserSprite,
-        position: Vec3
+    impl SpriteManifest {
+        fn sheet(&self, name: &str) -> Option<&SpriteSheet> {
+            self.sheets.get(name)
+        }
     }
 
-    #[derive(Component)]
-    struct PlayerSprite;
+    /// Every clip the game plays, by sheet. A manifest without one of them doesn't load, and
+    /// when it's a hot reload the manifest from before stays.
+    const PLAYED_CLIPS: [(&str, &[&str]); 7] = [
+        ("ship", &SHIP_BANK_CLIPS),
+        ("enemy-small", &["fly"]),
+        ("enemy-medium", &["fly"]),
+        ("enemy-big", &["fly"]),
+        ("laser-bolts", &["player-bolt", "enemy-bolt"]),
+        ("power-up", &["weapon", "bomb", "shield"]),
+        ("explosion", &["explode"]),
+    ];
 
-    #[derive(Component)]
-    struct Player {
-        movement_speed: f32,
-        player_sprite: PlayerSprite,
-        position: Vec3
-    }
+    #[derive(Default)]
+    struct SpriteManifestLoader;
 
-    fn setup_camera(
-        mut commands: Commands
-    ) {
-        commands.spawn(Camera2dBundle::default());
+    #[derive(Debug, thiserror::Error)]
+    enum SpriteManifestLoaderError {
+        #[error("could not read sprite manifest: {0}")]
+        Io(#[from] std::io::Error),
+        #[error("could not parse sprite manifest: {0}")]
+        Ron(#[from] ron::error::SpannedError),
+        #[error("clip {clip} of sheet {sheet} has no frames")]
+        EmptyClip { sheet: String, clip: String },
+        #[error("clip {clip} of sheet {sheet} plays at 0 fps")]
+        ZeroFps { sheet: String, clip: String },
+        #[error("clip {clip} of sheet {sheet} has a frame duration that isn't a positive number of seconds")]
+        InvalidDuration { sheet: String, clip: String },
+        #[error("clip {clip} of sheet {sheet} shows frame {frame} of a sheet with {cells} cells")]
+        FrameOutOfRange { sheet: String, clip: String, frame: usize, cells: usize },
+        #[error("there is no sheet {0}")]
+        MissingSheet(&'static str),
+        #[error("sheet {sheet} has no clip {clip}")]
+        MissingClip { sheet: &'static str, clip: &'static str },
     }
 
-    fn setup(
-        mut commands: Commands,
-        asset_server: Res<AssetServer>,
-        mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-        mut settings: ResMut<FramepaceSettings>
-    ) {
-        settings.limiter = Limiter::from_framerate(60.0);
+    impl AssetLoader for SpriteManifestLoader {
+        type Asset = SpriteManifest;
+        type Settings = ();
+        type Error = SpriteManifestLoaderError;
 
-        let texture = asset_server.load("Spaceship-shooter-gamekit/Assets/spritesheets/ship.png");
+        async fn load<'a>(
+            &'a self,
+            reader: &'a mut Reader<'_>,
+            _settings: &'a (),
+            load_context: &'a mut LoadContext<'_>,
+        ) -> Result<SpriteManifest, SpriteManifestLoaderError> {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let descriptor: SpriteManifestDescriptor = ron::de::from_bytes(&bytes)?;
 
-        let layout = TextureAtlasLayout::from_grid(UVec2::new(16, 24), 5, 2, None, None);
-        let texture_atlas_layout = texture_atlas_layouts.add(layout);
+            let mut sheets = HashMap::new();
+            for (name, sheet) in descriptor.sheets {
+                // anything a clip could panic on while it plays, a hot reload included
+                let cells = sheet.columns as usize * sheet.rows as usize;
+                for (clip_name, clip) in &sheet.clips {
+                    let clip_name = clip_name.clone();
+                    if clip.frames.is_empty() {
+                        return Err(SpriteManifestLoaderError::EmptyClip { sheet: name, clip: clip_name });
+                    }
+                    if clip.fps == 0 {
+                        return Err(SpriteManifestLoaderError::ZeroFps { sheet: name, clip: clip_name });
+                    }
+                    if clip.durations.iter().any(|seconds| !seconds.is_finite() || *seconds <= 0.0) {
+                        return Err(SpriteManifestLoaderError::InvalidDuration { sheet: name, clip: clip_name });
+                    }
+                    if let Some(&frame) = clip.frames.iter().find(|&&frame| frame >= cells) {
+                        return Err(SpriteManifestLoaderError::FrameOutOfRange { sheet: name, clip: clip_name, frame, cells });
+                    }
+                }
 
-        let animation_config_1 = AnimationConfig::new(0, 9, 10);
+                let layout = TextureAtlasLayout::from_grid(UVec2::from(sheet.cell_size), sheet.columns, sheet.rows, None, None);
+                sheets.insert(name.clone(), SpriteSheet {
+                    texture: load_context.load(sheet.texture),
+                    layout: load_context.add_labeled_asset(format!("{name}/layout"), layout),
+                    clips: sheet.clips,
+                });
+            }
 
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform::from_scale(Vec3::splat(3.0))
-                    .with_translation(Vec3::new(0.0, 0.0, 0.0)),
-                texture: texture.clone(),
-                ..default()
-            },
-            TextureAtlas {
-                layout: texture_atlas_layout.clone(),
-                index: animation_config_1.first_sprite_index,
-            },
-            PlayerSprite,
-            Cooldown{last_time: now_as_u128()},
-            animation_config_1,
-            Player {
-                movement_speed: 500.0,
-                player_sprite: PlayerSprite,
-                position: Vec3::new(0.0, 0.0, 0.0)
+            for (sheet, clips) in PLAYED_CLIPS {
+                let Some(sprite_sheet) = sheets.get(sheet) else {
+                    return Err(SpriteManifestLoaderError::MissingSheet(sheet));
+                };
+                if let Some(&clip) = clips.iter().find(|clip| sprite_sheet.clip(clip).is_none()) {
+                    return Err(SpriteManifestLoaderError::MissingClip { sheet, clip });
+                }
             }
-        ));
 
-        commands.spawn(TextBundle {
-            text: Text::from_section(
-                "Move: Arrow Keys\nShoot: Space",
-                TextStyle::default(),
-            ),
-            style: Style {
-                position_type: PositionType::Absolute,
-                top: Val::Px(12.0),
-                left: Val::Px(12.0),
-                ..default()
-            },
-            ..default()
-        });
+            Ok(SpriteManifest { sheets })
+        }
 
-        commands.spawn(
-            SpawnTimer {
-                timer: 30.0
-            }
-        );
+        fn extensions(&self) -> &[&str] {
+            &["sprites.ron"]
+        }
+    }
 
-        commands.spawn(
-            ScoreCounter {
-                score: 0.0
-            }
-        );
+    /// Which manifest clip an animated sprite plays. Changing `clip` switches the animation, and
+    /// every clip is refreshed when the manifest is edited.
+    #[derive(Component)]
+    struct SpriteClip {
+        sheet: &'static str,
+        clip: &'static str,
+    }
 
-        commands.spawn((TextBundle {
-            text: Text::from_section(
-                "Score: 000",
-                TextStyle::default(),
-            ),
-            style: Style {
-                position_type: PositionType::Absolute,
-            top: Val::Px(640.0),
-                left: Val::Px(1100.0),
-                ..default()
-            },
-            ..default()
-        },ScoreCounter {
-            score: 0.0,
-        }));
+    #[derive(SystemParam)]
+    struct Sprites<'w> {
+        game_assets: Res<'w, GameAssets>,
+        manifests: Res<'w, Assets<SpriteManifest>>,
     }
 
-    fn player_movement_system(
-        time: Res<Time>,
-        keyboard_input: Res<ButtonInput<KeyCode>>,
-        mut query: Query<(&mut Player, &mut Transform)>,
-    ) {
-        if query.iter().is_empty() {
-            return;
+    impl Sprites<'_> {
+        fn manifest(&self) -> &SpriteManifest {
+            self.manifests
+                .get(&self.game_assets.sprites)
+                .expect("sprite manifest is loaded before gameplay starts")
         }
 
-        let (mut ship, mut transform) = query.single_mut();
+        // one of the sheets the loader made sure are there
+        fn sheet(&self, name: &str) -> &SpriteSheet {
+            self.manifest()
+                .sheet(name)
+                .expect("the sprite manifest loader checks every sheet the game draws is there")
+        }
+    }
 
-        let mut movement_x = 0.0;
-        let mut movement_y = 0.0;
+    fn apply_sprite_clips(
+        mut events: EventReader<AssetEvent<SpriteManifest>>,
+        sprites: Sprites,
+        mut query: Query<(Ref<SpriteClip>, &mut AnimationConfig, &mut TextureAtlas)>,
+    ) {
+        let reloaded = events
+            .read()
+            .any(|event| event.is_modified(&sprites.game_assets.sprites));
 
-        if keyboard_input.pressed(KeyCode::ArrowLeft) {
-            movement_x -= 1.0;
-        }
+        for (sprite_clip, mut config, mut atlas) in &mut query {
+            if !reloaded && !sprite_clip.is_changed() {
+                continue;
+            }
 
-        if keyboard_input.pressed(KeyCode::ArrowRight) {
-            movement_x += 1.0;
+            let Some(sheet) = sprites.manifest().sheet(sprite_clip.sheet) else {
+                warn!("sprite manifest has no sheet named {}, keeping the sprite as it is", sprite_clip.sheet);
+                continue;
+            };
+            let Some(clip) = sheet.clip(sprite_clip.clip) else {
+                warn!("sheet {} has no clip named {}, keeping the sprite as it is", sprite_clip.sheet, sprite_clip.clip);
+                continue;
+            };
+            *config = clip.to_config();
+            atlas.layout = sheet.layout.clone();
+            atlas.index = config.sprite_index();
         }
+    }
 
-        if keyboard_input.pressed(KeyCode::ArrowUp) {
-            movement_y += 1.0;
-        }
+    #[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Deserialize, Reflect)]
+    enum WeaponKind {
+        Laser,
+        TwinShot,
+        Spread,
+        Homing,
+        ChargeBeam,
+    }
+
+    impl WeaponKind {
+        const ALL: [WeaponKind; 5] = [
+            WeaponKind::Laser,
+            WeaponKind::TwinShot,
+            WeaponKind::Spread,
+            WeaponKind::Homing,
+            WeaponKind::ChargeBeam,
+        ];
 
-        if keyboard_input.pressed(KeyCode::ArrowDown) {
-            movement_y -= 1.0;
+        fn label(self) -> &'static str {
+            match self {
+                WeaponKind::Laser => "LASER",
+                WeaponKind::TwinShot => "TWIN",
+                WeaponKind::Spread => "SPREAD",
+                WeaponKind::Homing => "HOMING",
+                WeaponKind::ChargeBeam => "BEAM",
+            }
         }
+    }
 
-        let movement_distance_x = movement_x * ship.movement_speed * time.delta_seconds();
-        let movement_distance_y = movement_y * ship.movement_speed * time.delta_seconds();
-        transform.translation.y += movement_distance_y;
-        transform.translation.x += movement_distance_x;
-        ship.position.x = transform.translation.x;
-        ship.position.y = transform.translation.y;
+    /// One upgrade level of a weapon: `projectiles` shots per volley, `spacing` pixels apart
+    /// side by side and fanned out over `spread` degrees in total.
+    #[derive(Deserialize, Clone)]
+    struct WeaponLevel {
+        projectiles: u32,
+        #[serde(default)]
+        spacing: f32,
+        #[serde(default)]
+        spread: f32,
+        damage: u32,
+    }
 
-        let extents = Vec3::from((BOUNDS / 2.0, 0.0));
-        transform.translation = transform.translation.min(extents).max(-extents);
+    /// How a weapon that fires on release scales with the time fire was held.
+    #[derive(Deserialize, Clone)]
+    struct WeaponCharge {
+        // time to reach full charge
+        seconds: f32,
+        // at full charge damage is multiplied by 1 + this, likewise the projectile size
+        damage_multiplier: f32,
+        scale_multiplier: f32,
     }
 
-    fn enemy_kill_system(
-        mut commands: Commands,
-        mut enemy_query: Query<(Entity, &Transform), With<Enemy>>,
-        asset_server: Res<AssetServer>,
-        mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-        mut shot_query: Query<(Entity, &Transform), With<Laser>>,
-        mut text_query: Query<(&mut Text, &mut ScoreCounter)>,
-    ) {
-        if shot_query.iter().is_empty() || enemy_query.iter().is_empty() {
-            return;
+    #[derive(Deserialize, Clone)]
+    struct WeaponDefinition {
+        cooldown: f32,
+        speed: f32,
+        color: (f32, f32, f32),
+        #[serde(default)]
+        pierce: bool,
+        // radians per second a shot may turn towards the nearest enemy
+        #[serde(default)]
+        homing: f32,
+        #[serde(default)]
+        charge: Option<WeaponCharge>,
+        levels: Vec<WeaponLevel>,
+    }
+
+    impl WeaponDefinition {
+        fn level(&self, level: usize) -> &WeaponLevel {
+            &self.levels[level.clamp(1, self.levels.len()) - 1]
         }
 
+        fn color(&self) -> Color {
+            Color::srgb(self.color.0, self.color.1, self.color.2)
+        }
+    }
 
-        for (enemy_entity, enemy_transform) in &enemy_query {
-            for (shot, shot_transform) in &shot_query {
-                if enemy_transform.translation.y.distance(shot_transform.translation.y) < 15.5 && enemy_transform.translation.x.distance(shot_transform.translation.x) < 45.5 {
-                    let (mut text_bundle,mut score_counter) = text_query.single_mut();
+    /// Projectile patterns of every player weapon, loaded from a `.weapons.ron` file.
+    #[derive(Asset, TypePath, Deserialize)]
+    struct WeaponManifest {
+        weapons: HashMap<WeaponKind, WeaponDefinition>,
+    }
 
-                    score_counter.score += 1.0;
-                    let text = format!("Score: {}", score_counter.score);
-                    text_bundle.sections = Text::from_section(text, Default::default()).sections;
+    #[derive(Default)]
+    struct WeaponManifestLoader;
 
-                    commands.entity(enemy_entity).despawn();
-                    commands.entity(shot).despawn();
-                    let texture = asset_server.load("Spaceship-shooter-gamekit/Assets/spritesheets/explosion.png");
+    #[derive(Debug, thiserror::Error)]
+    enum WeaponManifestLoaderError {
+        #[error("could not read weapon manifest: {0}")]
+        Io(#[from] std::io::Error),
+        #[error("could not parse weapon manifest: {0}")]
+        Ron(#[from] ron::error::SpannedError),
+        #[error("weapon manifest has no entry for {0:?}")]
+        MissingWeapon(WeaponKind),
+        #[error("weapon {0:?} has no levels")]
+        NoLevels(WeaponKind),
+    }
 
-                    let layout = TextureAtlasLayout::from_grid(UVec2::new(80 / 5, 16), 5, 1, None, None);
-                    let texture_atlas_layout = texture_atlas_layouts.add(layout);
+    impl AssetLoader for WeaponManifestLoader {
+        type Asset = WeaponManifest;
+        type Settings = ();
+        type Error = WeaponManifestLoaderError;
 
-                    let animation_config_1 = AnimationConfig::new(0, 4, 10);
+        async fn load<'a>(
+            &'a self,
+            reader: &'a mut Reader<'_>,
+            _settings: &'a (),
+            _load_context: &'a mut LoadContext<'_>,
+        ) -> Result<WeaponManifest, WeaponManifestLoaderError> {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let manifest: WeaponManifest = ron::de::from_bytes(&bytes)?;
 
-                    commands.spawn((
-                        SpriteBundle {
-                            transform: Transform::from_scale(Vec3::splat(6.0))
-                                .with_translation(Vec3::new(enemy_transform.translation.x, enemy_transform.translation.y, 0.0)),
-                            texture,
-                            ..default()
-                        },
-                        TextureAtlas {
-                            layout: texture_atlas_layout,
-                            index: animation_config_1.first_sprite_index,
-                        },
-                        animation_config_1,
-                        Explosion {
-                            frame_timer: 0.0
-                        }
-                    ));
+            for kind in WeaponKind::ALL {
+                match manifest.weapons.get(&kind) {
+                    None => return Err(WeaponManifestLoaderError::MissingWeapon(kind)),
+                    Some(weapon) if weapon.levels.is_empty() => return Err(WeaponManifestLoaderError::NoLevels(kind)),
+                    Some(_) => {}
                 }
             }
-        }
-    }
 
-    fn player_weapons_system(
-        time: Res<Time>,
-        mut query: Query<(&mut Laser, &mut Transform)>
-    ) {
-        for (mut shot, mut transform) in query.iter_mut() {
-            let movement_distance_y = 2.0 * shot.movement_speed * time.delta_seconds();
-            transform.translation.y += movement_distance_y;
-            shot.position.y = transform.translation.y;
-
-            let extents = Vec3::from((BOUNDS, 0.0));
-            transform.translation = transform.translation.min(extents).max(-extents);
+            Ok(manifest)
         }
 
-    }
-    fn player_shoot_system(
-        mut commands: Commands,
-        asset_server: Res<AssetServer>,
-        mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-        mut query: Query<(&mut Cooldown, &Player)>,
-        keyboard_input: Res<ButtonInput<KeyCode>>
-    ) {
-
-        for (mut cooldown, ship) in query.iter_mut() {
-            let texture = asset_server.load("Spaceship-shooter-gamekit/Assets/spritesheets/laser-bolts.png");
-
-            let layout = TextureAtlasLayout::from_grid(UVec2::new(16, 16), 2, 2, None, None);
-            let texture_atlas_layout = texture_atlas_layouts.add(layout);
-
-            let animation_config_1 = AnimationConfig::new(2, 3, 10);
-            if keyboard_input.pressed(KeyCode::Space) {
-                if cooldown.last_time + 250 <= now_as_u128() {
-                    cooldown.last_time = now_as_u128();
-                    commands.spawn((
-                        SpriteBundle {
-                            transform: Transform::from_scale(Vec3::splat(3.0))
-                                .with_translation(Vec3::new(ship.position.x, ship.position.y + 6.0, 0.0)),
-                            texture,
-                            ..default()
-                        },
-                        TextureAtlas {
-                            layout: texture_atlas_layout,
-                            index: animation_config_1.first_sprite_index,
-                        },
-                        LaserSprite,
-                        animation_config_1,
-                        Laser {
-                            movement_speed: 500.0,
-                            laser_sprite: LaserSprite,
-                            position: Vec3::new(ship.position.x, ship.position.y + 6.0, 0.0)
-                        }
-                    ));
-                }
-            }
+        fn extensions(&self) -> &[&str] {
+            &["weapons.ron"]
         }
     }
 
-    fn now_as_u128() -> u128 {
-        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis()
+    #[derive(SystemParam)]
+    struct Weapons<'w> {
+        game_assets: Res<'w, GameAssets>,
+        manifests: Res<'w, Assets<WeaponManifest>>,
     }
 
-    fn enemy_movement_system(
-        time: Res<Time>,
-        mut query: Query<(&mut Enemy, &mut Transform)>,
-        ship_query: Query<(&Player)>
-    ) {
-        if query.is_empty() || ship_query.is_empty(){
-            return;
+    impl Weapons<'_> {
+        fn definition(&self, kind: WeaponKind) -> &WeaponDefinition {
+            &self.manifests
+                .get(&self.game_assets.weapons)
+                .expect("weapon manifest is loaded before gameplay starts")
+                .weapons[&kind]
         }
+    }
 
-        for (mut enemy, mut transform) in &mut query{
-            let (ship) = ship_query.single();
-            let direction_x = ship.position.x - enemy.position.x;
-            let direction_y = ship.position.y - enemy.position.y;
+    #[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+    enum PowerUpKind {
+        // switches to that weapon, or upgrades it if it is already equipped
+        Weapon(WeaponKind),
+        // adds a bomb to the stock
+        Bomb,
+        // makes the ship invulnerable for a while, shown as an active power-up
+        Shield,
+    }
 
-            let angle = direction_y.atan2(direction_x);
+    impl PowerUpKind {
+        fn clip(self) -> &'static str {
+            match self {
+                PowerUpKind::Weapon(_) => "weapon",
+                PowerUpKind::Bomb => "bomb",
+                PowerUpKind::Shield => "shield",
+            }
+        }
+    }
 
+    /// A pickup dropped by a destroyed enemy, drifting down until collected or gone.
+    #[derive(Component, Clone, Reflect)]
+    #[reflect(Component)]
+    struct PowerUp {
+        kind: PowerUpKind
+    }
 
-            let normalized_x = angle.cos();
-            let normalized_y = angle.sin();
+    /// How an enemy flies, picked per enemy from its wave's table. Speeds come from the enemy,
+    /// so a pattern only describes the shape of the flight. Positions are in playfield units,
+    /// -1 to 1 from edge to edge, so patterns fit either layout.
+    #[derive(Deserialize, Clone, Debug, Reflect)]
+    enum MovementPattern {
+        // straight down
+        Dive,
+        // down while weaving side to side
+        Sine { amplitude: f32, wavelength: f32 },
+        // along a named path of the wave manifest, carrying on straight once it ends
+        Path(String),
+        // to a fixed spot in the arena, then stay there
+        Hold { x: f32, y: f32 },
+        // down to a height, sweep side to side for a while, then back up and away
+        StrafeRetreat { y: f32, seconds: f32 },
+        // straight at the player, wherever they go
+        Kamikaze,
+    }
 
-            let movement_distance_x = normalized_x * enemy.movement_speed * time.delta_seconds();
-            let movement_distance_y = normalized_y * enemy.movement_speed * time.delta_seconds();
+    /// An enemy's progress along its `MovementPattern`.
+    ///
+    /// Kept out of the tables like the other components enemies and ships gain and lose in play,
+    /// so every enemy stays in one table and is iterated in the same order however the world got
+    /// its archetypes, which rollback between two machines depends on.
+    #[derive(Component, Clone, Reflect)]
+    #[component(storage = "SparseSet")]
+    #[reflect(Component)]
+    struct EnemyMovement {
+        pattern: MovementPattern,
+        origin: Vec2,
+        // flips the pattern left to right
+        mirrored: bool,
+        elapsed: f32,
+        travelled: f32,
+        // when a strafing enemy reached its height
+        arrived_at: Option<f32>,
+        heading: f32,
+    }
 
-            transform.translation.x += movement_distance_x;
-            transform.translation.y += movement_distance_y;
+    impl EnemyMovement {
+        fn new(pattern: MovementPattern, origin: Vec2, mirrored: bool) -> Self {
+            Self {
+                pattern,
+                origin,
+                mirrored,
+                elapsed: 0.0,
+                travelled: 0.0,
+                arrived_at: None,
+                heading: if mirrored { -1.0 } else { 1.0 },
+            }
+        }
 
-            enemy.position.x = transform.translation.x;
-            enemy.position.y = transform.translation.y;
+        /// Advances the pattern by one tick and returns the new position.
+        fn step(&mut self, position: Vec2, speed: f32, delta: f32, manifest: &WaveManifest, playfield: &Playfield, player: Option<Vec2>) -> Vec2 {
+            let EnemyMovement { pattern, origin, mirrored, elapsed, travelled, arrived_at, heading } = self;
+            *elapsed += delta;
+            let step = speed * delta;
+            let side = if *mirrored { -1.0 } else { 1.0 };
 
-            let extents = 640.0;
-            transform.translation.y = transform.translation.y.min(extents).max(-extents);
+            match pattern {
+                MovementPattern::Dive => position - Vec2::Y * step,
+                MovementPattern::Sine { amplitude, wavelength } => {
+                    let y = position.y - step;
+                    let phase = (origin.y - y) / *wavelength * std::f32::consts::TAU;
+                    Vec2::new(origin.x + side * *amplitude * phase.sin(), y)
+                }
+                MovementPattern::Path(name) => match manifest.path(name) {
+                    Some(path) => {
+                        *travelled += step;
+                        path.point_at(*travelled, playfield) * Vec2::new(side, 1.0)
+                    }
+                    // edited out of the manifest while enemies were still on it
+                    None => {
+                        warn!("wave manifest has no path named {name} any more, its enemies dive instead");
+                        *pattern = MovementPattern::Dive;
+                        position - Vec2::Y * step
+                    }
+                },
+                MovementPattern::Hold { x, y } => move_towards(position, playfield.to_world(Vec2::new(side * *x, *y)), step),
+                MovementPattern::StrafeRetreat { y, seconds } => match *arrived_at {
+                    None if position.y - step > playfield.to_world(Vec2::new(0.0, *y)).y => position - Vec2::Y * step,
+                    None => {
+                        *arrived_at = Some(*elapsed);
+                        Vec2::new(position.x, playfield.to_world(Vec2::new(0.0, *y)).y)
+                    }
+                    Some(arrived) if *elapsed - arrived < *seconds => {
+                        let (left, right) = (playfield.rect.min.x + ENEMY_EDGE_INSET, playfield.rect.max.x - ENEMY_EDGE_INSET);
+                        let x = position.x + *heading * step;
+                        if x <= left {
+                            *heading = 1.0;
+                        } else if x >= right {
+                            *heading = -1.0;
+                        }
+                        Vec2::new(x.clamp(left, right), position.y)
+                    }
+                    Some(_) => position + Vec2::Y * step,
+                },
+                MovementPattern::Kamikaze => match player {
+                    Some(player) => move_towards(position, player, step),
+                    None => position - Vec2::Y * step,
+                },
+            }
         }
     }
 
-    fn explosion_and_laser_termination_system(
-        mut commands: Commands,
-        mut query: Query<(Entity, &mut Explosion), With<Explosion>>,
-        mut shot_query: Query<(Entity, &mut Laser), With<Laser>>
-    ) {
-        if !query.iter().is_empty() {
-            for (entity, mut explosion) in &mut query {
-                explosion.frame_timer += 1.0;
+    #[derive(Deserialize, Clone, Copy, Debug, Reflect)]
+    enum FormationShape {
+        // the first member at the tip, the others trailing behind on alternating sides
+        V,
+        Line,
+        Grid,
+        // a ring that slowly turns
+        Circle,
+    }
 
-                if explosion.frame_timer > 30.0 {
-                    commands.entity(entity).despawn();
+    impl FormationShape {
+        /// Offset from the formation's anchor of the `index`th of `count` members.
+        fn slot(self, index: usize, count: usize, spacing: f32, elapsed: f32) -> Vec2 {
+            let index_f = index as f32;
+            let count_f = count as f32;
+            match self {
+                FormationShape::V => {
+                    let rank = index.div_ceil(2) as f32;
+                    let side = if index % 2 == 1 { -1.0 } else { 1.0 };
+                    Vec2::new(side * rank * spacing, rank * spacing * 0.75)
                 }
-            }
-        }
-        if !shot_query.iter().is_empty() {
-            for (laser_entity, shot) in &mut shot_query {
-                if shot.position.y >= BOUNDS.y/2.0  {
-                    commands.entity(laser_entity).despawn();
+                FormationShape::Line => Vec2::new((index_f - (count_f - 1.0) / 2.0) * spacing, 0.0),
+                FormationShape::Grid => {
+                    let columns = count_f.sqrt().ceil() as usize;
+                    let (row, column) = (index / columns, index % columns);
+                    // the last row may be short, keep it centred
+                    let in_row = columns.min(count - row * columns) as f32;
+                    Vec2::new((column as f32 - (in_row - 1.0) / 2.0) * spacing, row as f32 * spacing)
+                }
+                FormationShape::Circle => {
+                    let radius = (spacing * count_f / std::f32::consts::TAU).max(spacing);
+                    let angle = index_f / count_f * std::f32::consts::TAU + elapsed * FORMATION_CIRCLE_SPIN;
+                    Vec2::from_angle(angle) * radius
                 }
             }
         }
     }
 
+    #[derive(Deserialize, Clone)]
+    struct FormationDefinition {
+        shape: FormationShape,
+        size: u32,
+        spacing: f32,
+        // one member breaks off to dive at the player this often
+        break_seconds: f32,
+    }
 
-    fn player_kill_system(
-        mut game_state: ResMut<NextState<GameState>>,
-        mut commands: Commands,
-        mut query: Query<(Entity, &Transform), With<Player>>,
-        enemy_query: Query<&Transform, With<Enemy>>,
-        asset_server: Res<AssetServer>,
-        mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>
-    ) {
-        if !query.iter().is_empty() && !enemy_query.iter().is_empty() {
-            let (entity, player) = query.single_mut();
-            for enemy in &enemy_query {
-                if player.translation.y.distance(enemy.translation.y) < 15.5 && player.translation.x.distance(enemy.translation.x) < 45.5  {
-
-                    commands.entity(entity).despawn();
-                    let texture = asset_server.load("Spaceship-shooter-gamekit/Assets/spritesheets/explosion.png");
+    /// The anchor a group of enemies flies in formation around. It follows the movement
+    /// pattern itself and has no sprite, so losing any member never loses the formation.
+    #[derive(Component, Clone, Reflect)]
+    #[reflect(Component, MapEntities)]
+    struct Formation {
+        shape: FormationShape,
+        spacing: f32,
+        speed: f32,
+        size: u32,
+        // every member still alive, including those that broke off
+        members: Vec<Entity>,
+        killed: u32,
+        // where the latest member went down and who got it, the clear bonus goes there
+        last_kill: Vec3,
+        last_killer: Option<PlayerId>,
+        break_timer: Timer,
+    }
 
-                    let layout = TextureAtlasLayout::from_grid(UVec2::new(80 / 5, 16), 5, 1, None, None);
-                    let texture_atlas_layout = texture_atlas_layouts.add(layout);
+    impl MapEntities for Formation {
+        fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+            for member in &mut self.members {
+                *member = entity_mapper.map_entity(*member);
+            }
+        }
+    }
+
+    /// An enemy still holding its place in a formation.
+    #[derive(Component, Reflect)]
+    #[component(storage = "SparseSet")]
+    #[reflect(Component)]
+    struct FormationMember;
+
+    /// A path sampled into straight pieces, so enemies can follow it at a constant speed.
+    struct EnemyPath {
+        // in playfield units
+        points: Vec<Vec2>,
+    }
+
+    impl EnemyPath {
+        /// The world position `distance` world units along the path laid over `playfield`.
+        fn point_at(&self, distance: f32, playfield: &Playfield) -> Vec2 {
+            let mut remaining = distance;
+            let mut points = self.points.iter().map(|point| playfield.to_world(*point));
+            let mut before = points.next().expect("paths have at least four points");
+            let mut last = before;
+            for point in points {
+                (before, last) = (last, point);
+                let length = before.distance(last);
+                if remaining <= length {
+                    return before.lerp(last, remaining / length.max(f32::EPSILON));
+                }
+                remaining -= length;
+            }
+            last + (last - before).normalize_or_zero() * remaining
+        }
+    }
+
+    #[derive(Deserialize, Clone)]
+    struct WaveEnemy {
+        kind: EnemyKind,
+        pattern: MovementPattern,
+        // relative chance of being picked among the wave's enemies
+        weight: u32,
+        // half of these spawn with the pattern mirrored left to right
+        #[serde(default)]
+        mirror: bool,
+        // spawns a whole formation of this enemy, with the pattern flown by its anchor
+        #[serde(default)]
+        formation: Option<FormationDefinition>,
+    }
+
+    #[derive(Deserialize, Clone)]
+    struct WaveDefinition {
+        enemies: Vec<WaveEnemy>,
+    }
+
+    impl WaveDefinition {
+        fn pick(&self, rng: &mut impl Rng) -> &WaveEnemy {
+            let mut roll = rng.gen_range(0..self.enemies.iter().map(|enemy| enemy.weight).sum::<u32>());
+            for enemy in &self.enemies {
+                if roll < enemy.weight {
+                    return enemy;
+                }
+                roll -= enemy.weight;
+            }
+            unreachable!("the roll is below the total weight")
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct WaveManifestDescriptor {
+        paths: HashMap<String, Vec<(f32, f32)>>,
+        waves: Vec<WaveDefinition>,
+    }
+
+    /// Which enemies each wave sends and how they fly, loaded from a `.waves.ron` file.
+    #[derive(Asset, TypePath)]
+    struct WaveManifest {
+        paths: HashMap<String, EnemyPath>,
+        waves: Vec<WaveDefinition>,
+    }
+
+    impl WaveManifest {
+        fn path(&self, name: &str) -> Option<&EnemyPath> {
+            self.paths.get(name)
+        }
+
+        // waves past the end of the list repeat the last one
+        fn wave(&self, number: u32) -> &WaveDefinition {
+            &self.waves[(number.max(1) as usize - 1).min(self.waves.len() - 1)]
+        }
+    }
+
+    #[derive(Default)]
+    struct WaveManifestLoader;
+
+    #[derive(Debug, thiserror::Error)]
+    enum WaveManifestLoaderError {
+        #[error("could not read wave manifest: {0}")]
+        Io(#[from] std::io::Error),
+        #[error("could not parse wave manifest: {0}")]
+        Ron(#[from] ron::error::SpannedError),
+        #[error("wave manifest lists no waves")]
+        NoWaves,
+        #[error("wave {0} has no enemies with a weight above zero")]
+        EmptyWave(usize),
+        #[error("path {0} needs a start point followed by three points per Bézier segment")]
+        BadPath(String),
+        #[error("wave {0} has a formation without members")]
+        EmptyFormation(usize),
+        #[error("wave {wave} uses a path named {path} that isn't defined")]
+        UnknownPath { wave: usize, path: String },
+    }
+
+    impl AssetLoader for WaveManifestLoader {
+        type Asset = WaveManifest;
+        type Settings = ();
+        type Error = WaveManifestLoaderError;
+
+        async fn load<'a>(
+            &'a self,
+            reader: &'a mut Reader<'_>,
+            _settings: &'a (),
+            _load_context: &'a mut LoadContext<'_>,
+        ) -> Result<WaveManifest, WaveManifestLoaderError> {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let descriptor: WaveManifestDescriptor = ron::de::from_bytes(&bytes)?;
+
+            let mut paths = HashMap::new();
+            for (name, points) in descriptor.paths {
+                if points.len() < 4 || (points.len() - 1) % 3 != 0 {
+                    return Err(WaveManifestLoaderError::BadPath(name));
+                }
+                let segments: Vec<[Vec2; 4]> = points
+                    .windows(4)
+                    .step_by(3)
+                    .map(|segment| [0, 1, 2, 3].map(|i| Vec2::from(segment[i])))
+                    .collect();
+                let curve = CubicBezier::new(segments.clone()).to_curve();
+                paths.insert(name, EnemyPath {
+                    points: curve.iter_positions(segments.len() * PATH_SAMPLES_PER_SEGMENT).collect(),
+                });
+            }
+
+            if descriptor.waves.is_empty() {
+                return Err(WaveManifestLoaderError::NoWaves);
+            }
+            for (index, wave) in descriptor.waves.iter().enumerate() {
+                if wave.enemies.iter().all(|enemy| enemy.weight == 0) {
+                    return Err(WaveManifestLoaderError::EmptyWave(index + 1));
+                }
+                for enemy in &wave.enemies {
+                    if enemy.formation.as_ref().is_some_and(|formation| formation.size == 0) {
+                        return Err(WaveManifestLoaderError::EmptyFormation(index + 1));
+                    }
+                    if let MovementPattern::Path(path) = &enemy.pattern {
+                        if !paths.contains_key(path) {
+                            return Err(WaveManifestLoaderError::UnknownPath { wave: index + 1, path: path.clone() });
+                        }
+                    }
+                }
+            }
+
+            Ok(WaveManifest { paths, waves: descriptor.waves })
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["waves.ron"]
+        }
+    }
+
+    #[derive(SystemParam)]
+    struct WaveTable<'w> {
+        game_assets: Res<'w, GameAssets>,
+        manifests: Res<'w, Assets<WaveManifest>>,
+    }
+
+    impl WaveTable<'_> {
+        fn manifest(&self) -> &WaveManifest {
+            self.manifests
+                .get(&self.game_assets.waves)
+                .expect("wave manifest is loaded before gameplay starts")
+        }
+    }
+
+    #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
+    enum MenuState {
+        Main,
+        NewGame,
+        Online,
+        HighScores,
+        Settings,
+        SettingsDisplay,
+        #[default]
+        Disabled,
+    }
+
+    #[derive(Component)]
+    struct OnMainMenuScreen;
+
+    #[derive(Component)]
+    struct OnNewGameScreen;
+
+    #[derive(Component)]
+    struct OnOnlineScreen;
+
+    #[derive(Component)]
+    struct OnHighScoresScreen;
+
+    #[derive(Component)]
+    struct OnSettingsMenuScreen;
+
+    #[derive(Component)]
+    struct OnDisplaySettingsMenuScreen;
+
+
+
+    const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+    const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+    const HOVERED_PRESSED_BUTTON: Color = Color::srgb(0.25, 0.65, 0.25);
+    const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
+
+    #[derive(Component)]
+    struct SelectedOption;
+
+    #[derive(Component)]
+    enum MenuButtonAction {
+        NewGame,
+        Continue,
+        Play,
+        Online,
+        Host,
+        Join,
+        HighScores,
+        Settings,
+        SettingsDisplay,
+        BackToMainMenu,
+        BackToNewGame,
+        BackToSettings,
+    }
+
+
+    #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
+    enum GameState {
+        #[default]
+        Splash,
+        Loading,
+        Game,
+        NameEntry,
+        Over
+    }
+
+    #[derive(Component, Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+    enum Layout {
+        #[default]
+        Landscape,
+        // a narrow, tall playfield like a vertical arcade shooter
+        Portrait,
+    }
+
+    #[derive(Component, Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+    enum ParticleDensity {
+        Off,
+        Low,
+        #[default]
+        Normal,
+        High,
+    }
+
+    impl ParticleDensity {
+        // the most particles alive at once, anything over it just isn't spawned
+        fn budget(self) -> usize {
+            match self {
+                ParticleDensity::Off => 0,
+                ParticleDensity::Low => 150,
+                ParticleDensity::Normal => 500,
+                ParticleDensity::High => 1500,
+            }
+        }
+    }
+
+    /// How strongly screen shake, hit-stop and flashes play, for players who find them too much.
+    #[derive(Component, Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+    enum EffectIntensity {
+        Off,
+        Reduced,
+        #[default]
+        Full,
+    }
+
+    impl EffectIntensity {
+        fn scale(self) -> f32 {
+            match self {
+                EffectIntensity::Off => 0.0,
+                EffectIntensity::Reduced => 0.4,
+                EffectIntensity::Full => 1.0,
+            }
+        }
+    }
+
+    /// Options from the display settings screen, saved along with the high scores.
+    #[derive(Resource, Clone, Default, Serialize, Deserialize)]
+    struct DisplaySettings {
+        #[serde(default)]
+        layout: Layout,
+        #[serde(default)]
+        particles: ParticleDensity,
+        #[serde(default)]
+        effects: EffectIntensity
+    }
+
+    /// Settings kept in the save file, written back whenever a menu changes them.
+    trait SavedSettings: Resource {
+        fn store(&self, save_data: &mut SaveData);
+    }
+
+    impl SavedSettings for DisplaySettings {
+        fn store(&self, save_data: &mut SaveData) {
+            save_data.display = self.clone();
+        }
+    }
+
+    /// A setting picked from a row of buttons in the menus.
+    trait MenuOption: Component + Copy + PartialEq {
+        type Settings: SavedSettings;
+        const ALL: &'static [Self];
+
+        fn label(self) -> &'static str;
+        fn get(settings: &Self::Settings) -> Self;
+        fn set(self, settings: &mut Self::Settings);
+    }
+
+    impl MenuOption for Layout {
+        type Settings = DisplaySettings;
+        const ALL: &'static [Self] = &[Layout::Landscape, Layout::Portrait];
+
+        fn label(self) -> &'static str {
+            match self {
+                Layout::Landscape => "Landscape",
+                Layout::Portrait => "Portrait",
+            }
+        }
+
+        fn get(settings: &DisplaySettings) -> Self {
+            settings.layout
+        }
+
+        fn set(self, settings: &mut DisplaySettings) {
+            settings.layout = self;
+        }
+    }
+
+    impl MenuOption for ParticleDensity {
+        type Settings = DisplaySettings;
+        const ALL: &'static [Self] = &[ParticleDensity::Off, ParticleDensity::Low, ParticleDensity::Normal, ParticleDensity::High];
+
+        fn label(self) -> &'static str {
+            match self {
+                ParticleDensity::Off => "Off",
+                ParticleDensity::Low => "Low",
+                ParticleDensity::Normal => "Normal",
+                ParticleDensity::High => "High",
+            }
+        }
+
+        fn get(settings: &DisplaySettings) -> Self {
+            settings.particles
+        }
+
+        fn set(self, settings: &mut DisplaySettings) {
+            settings.particles = self;
+        }
+    }
+
+    impl MenuOption for EffectIntensity {
+        type Settings = DisplaySettings;
+        const ALL: &'static [Self] = &[EffectIntensity::Off, EffectIntensity::Reduced, EffectIntensity::Full];
+
+        fn label(self) -> &'static str {
+            match self {
+                EffectIntensity::Off => "Off",
+                EffectIntensity::Reduced => "Reduced",
+                EffectIntensity::Full => "Full",
+            }
+        }
+
+        fn get(settings: &DisplaySettings) -> Self {
+            settings.effects
+        }
+
+        fn set(self, settings: &mut DisplaySettings) {
+            settings.effects = self;
+        }
+    }
+
+    #[derive(Component, Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+    enum PlayerCount {
+        #[default]
+        One,
+        Two,
+    }
+
+    impl PlayerCount {
+        fn players(self) -> &'static [PlayerId] {
+            match self {
+                PlayerCount::One => &PlayerId::ALL[..1],
+                PlayerCount::Two => &PlayerId::ALL,
+            }
+        }
+    }
+
+    /// Whether a co-op player out of lives may take one of their partner's to keep playing.
+    #[derive(Component, Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+    enum LifeSharing {
+        #[default]
+        Separate,
+        Shared,
+    }
+
+    /// How many play and how they share lives, picked on the new game screen and saved.
+    #[derive(Resource, Clone, Default, Serialize, Deserialize)]
+    struct CoopSettings {
+        #[serde(default)]
+        players: PlayerCount,
+        #[serde(default)]
+        lives: LifeSharing
+    }
+
+    impl SavedSettings for CoopSettings {
+        fn store(&self, save_data: &mut SaveData) {
+            save_data.coop = self.clone();
+        }
+    }
+
+    impl MenuOption for PlayerCount {
+        type Settings = CoopSettings;
+        const ALL: &'static [Self] = &[PlayerCount::One, PlayerCount::Two];
+
+        fn label(self) -> &'static str {
+            match self {
+                PlayerCount::One => "1 Player",
+                PlayerCount::Two => "2 Players",
+            }
+        }
+
+        fn get(settings: &CoopSettings) -> Self {
+            settings.players
+        }
+
+        fn set(self, settings: &mut CoopSettings) {
+            settings.players = self;
+        }
+    }
+
+    impl MenuOption for LifeSharing {
+        type Settings = CoopSettings;
+        const ALL: &'static [Self] = &[LifeSharing::Separate, LifeSharing::Shared];
+
+        fn label(self) -> &'static str {
+            match self {
+                LifeSharing::Separate => "Separate",
+                LifeSharing::Shared => "Shared",
+            }
+        }
+
+        fn get(settings: &CoopSettings) -> Self {
+            settings.lives
+        }
+
+        fn set(self, settings: &mut CoopSettings) {
+            settings.lives = self;
+        }
+    }
+
+    /// The player's own settings, put aside while a run plays by someone else's: the host's online
+    /// or a checkpoint's. They're put back once the run is over, so the menus never show the run's
+    /// or save them.
+    #[derive(Resource)]
+    struct OwnSettings {
+        mode: GameMode,
+        difficulty: Difficulty,
+        coop: CoopSettings,
+        layout: Layout,
+        simulation: SimulationSettings
+    }
+
+    // the coming run plays by these, the player's own are kept until it's over
+    fn play_by(world: &mut World, mode: GameMode, difficulty: Difficulty, coop: CoopSettings, layout: Layout) {
+        if !world.contains_resource::<OwnSettings>() {
+            let own = OwnSettings {
+                mode: *world.resource::<GameMode>(),
+                difficulty: *world.resource::<Difficulty>(),
+                coop: world.resource::<CoopSettings>().clone(),
+                layout: world.resource::<DisplaySettings>().layout,
+                simulation: world.resource::<SimulationSettings>().clone()
+            };
+            world.insert_resource(own);
+        }
+        world.insert_resource(mode);
+        world.insert_resource(difficulty);
+        world.insert_resource(coop);
+        world.insert_resource(Playfield::new(layout));
+        world.resource_mut::<DisplaySettings>().layout = layout;
+    }
+
+    fn restore_own_settings(
+        mut commands: Commands,
+        own: Res<OwnSettings>,
+        mut display: ResMut<DisplaySettings>
+    ) {
+        commands.insert_resource(own.mode);
+        commands.insert_resource(own.difficulty);
+        commands.insert_resource(own.coop.clone());
+        display.layout = own.layout;
+        commands.insert_resource(own.simulation.clone());
+        commands.remove_resource::<OwnSettings>();
+    }
+
+    /// The rules a run is played under, chosen on the new game screen. High scores are kept per mode.
+    #[derive(Resource, Component, Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+    enum GameMode {
+        #[default]
+        Campaign,
+        // escalating tiers from the first one on, with a mini-boss every few tiers
+        Endless,
+    }
+
+    impl GameMode {
+        fn label(self) -> &'static str {
+            match self {
+                GameMode::Campaign => "CAMPAIGN",
+                GameMode::Endless => "ENDLESS",
+            }
+        }
+
+        // what the HUD calls a wave
+        fn wave_label(self) -> &'static str {
+            match self {
+                GameMode::Campaign => "WAVE",
+                GameMode::Endless => "TIER",
+            }
+        }
+
+        /// How many waves in the difficulty has ramped up by: the campaign only gets harder once
+        /// its listed waves run out, endless from the second tier on.
+        fn ramp_waves(self, wave: u32, listed_waves: u32) -> u32 {
+            match self {
+                GameMode::Campaign => wave.saturating_sub(listed_waves),
+                GameMode::Endless => wave - 1,
+            }
+        }
+    }
+
+    impl SavedSettings for GameMode {
+        fn store(&self, save_data: &mut SaveData) {
+            save_data.mode = *self;
+        }
+    }
+
+    impl MenuOption for GameMode {
+        type Settings = GameMode;
+        const ALL: &'static [Self] = &[GameMode::Campaign, GameMode::Endless];
+
+        fn label(self) -> &'static str {
+            GameMode::label(self)
+        }
+
+        fn get(settings: &GameMode) -> Self {
+            *settings
+        }
+
+        fn set(self, settings: &mut GameMode) {
+            *settings = self;
+        }
+    }
+
+    /// Chosen on the main menu and remembered in the save file. High scores are kept per difficulty.
+    #[derive(Resource, Component, Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+    enum Difficulty {
+        Easy,
+        #[default]
+        Normal,
+        Hard,
+        Insane,
+    }
+
+    impl Difficulty {
+        fn label(self) -> &'static str {
+            match self {
+                Difficulty::Easy => "EASY",
+                Difficulty::Normal => "NORMAL",
+                Difficulty::Hard => "HARD",
+                Difficulty::Insane => "INSANE",
+            }
+        }
+
+        fn challenge(self) -> Challenge {
+            let (enemy_health, enemy_speed, fire_rate, bullet_speed, spawn_rate, formation_bonus, score_percent) = match self {
+                Difficulty::Easy => (0.75, 0.8, 0.6, 0.8, 0.75, 0, 50),
+                Difficulty::Normal => (1.0, 1.0, 1.0, 1.0, 1.0, 0, 100),
+                Difficulty::Hard => (1.5, 1.15, 1.4, 1.2, 1.25, 1, 150),
+                Difficulty::Insane => (2.0, 1.3, 2.0, 1.4, 1.6, 2, 200),
+            };
+            Challenge {
+                enemy_health,
+                enemy_speed,
+                fire_rate,
+                bullet_speed,
+                spawn_interval: (ENEMY_SPAWN_INTERVAL / spawn_rate).round(),
+                formation_bonus,
+                score_percent
+            }
+        }
+    }
+
+    impl SavedSettings for Difficulty {
+        fn store(&self, save_data: &mut SaveData) {
+            save_data.difficulty = *self;
+        }
+    }
+
+    impl MenuOption for Difficulty {
+        type Settings = Difficulty;
+        const ALL: &'static [Self] = &[Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Insane];
+
+        fn label(self) -> &'static str {
+            Difficulty::label(self)
+        }
+
+        fn get(settings: &Difficulty) -> Self {
+            *settings
+        }
+
+        fn set(self, settings: &mut Difficulty) {
+            *settings = self;
+        }
+    }
+
+    /// How hard the current run is right now: the difficulty's preset, ramped up once the
+    /// listed waves run out. Health, speeds and rates are multipliers of the enemies' own.
+    #[derive(Resource, Clone, Copy, Reflect)]
+    #[reflect(Resource)]
+    struct Challenge {
+        enemy_health: f32,
+        enemy_speed: f32,
+        fire_rate: f32,
+        bullet_speed: f32,
+        // whole fixed ticks between spawns, the spawn timer counts down one per tick
+        spawn_interval: f32,
+        // extra members in every formation
+        formation_bonus: u32,
+        // applied to every score event
+        score_percent: u64
+    }
+
+    impl Challenge {
+        fn ramped(self, waves: u32) -> Self {
+            let ramp = 1.0 + DIFFICULTY_RAMP_PER_WAVE * waves.min(DIFFICULTY_RAMP_MAX_WAVES) as f32;
+            Self {
+                enemy_health: self.enemy_health * ramp,
+                enemy_speed: self.enemy_speed * ramp,
+                fire_rate: self.fire_rate * ramp,
+                bullet_speed: self.bullet_speed * ramp,
+                spawn_interval: (self.spawn_interval / ramp).round(),
+                ..self
+            }
+        }
+
+        fn enemy_health(&self, kind: EnemyKind) -> u32 {
+            (kind.health() as f32 * self.enemy_health).round().max(1.0) as u32
+        }
+    }
+
+    /// Identifies the current run: the seed its `GameRng` started from and when it began.
+    #[derive(Resource, Reflect)]
+    #[reflect(Resource)]
+    struct RunInfo {
+        seed: u64,
+        started_at: u64
+    }
+
+    /// The only source of randomness gameplay may use, so a run can be replayed from its seed.
+    /// Saved as the seed and how far along it is, which is all there is to the generator.
+    #[derive(Resource, Clone, Deref, DerefMut, Reflect, Serialize, Deserialize)]
+    #[reflect_value(Resource, Serialize, Deserialize)]
+    #[serde(from = "RngState", into = "RngState")]
+    struct GameRng(ChaCha8Rng);
+
+    #[derive(Serialize, Deserialize)]
+    struct RngState {
+        seed: [u8; 32],
+        stream: u64,
+        // the word position is 68 bits, split in two for formats without 128-bit integers
+        word_high: u64,
+        word_low: u64
+    }
+
+    impl From<RngState> for GameRng {
+        fn from(state: RngState) -> Self {
+            let mut rng = ChaCha8Rng::from_seed(state.seed);
+            rng.set_stream(state.stream);
+            rng.set_word_pos(((state.word_high as u128) << 64) | state.word_low as u128);
+            Self(rng)
+        }
+    }
+
+    impl From<GameRng> for RngState {
+        fn from(rng: GameRng) -> Self {
+            let word = rng.get_word_pos();
+            Self {
+                seed: rng.get_seed(),
+                stream: rng.get_stream(),
+                word_high: (word >> 64) as u64,
+                word_low: word as u64
+            }
+        }
+    }
+
+    /// Points of the current run. Only `score_system` changes it, everything else sends a `ScoreEvent`.
+    #[derive(Resource, Default, Reflect)]
+    #[reflect(Resource)]
+    struct Score {
+        points: u64,
+    }
+
+    #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+    enum ScoreReason {
+        EnemyKilled,
+        NoDamageWave,
+        Accuracy,
+        FormationCleared,
+        MiniBoss,
+    }
+
+    /// Sent whenever a player destroys an enemy, by shot or by bomb.
+    #[derive(Event)]
+    struct EnemyDestroyed {
+        entity: Entity,
+        position: Vec3,
+        by: PlayerId
+    }
+
+    /// Short-lived HUD text rising from where points were scored in the world.
+    #[derive(Component)]
+    struct ScorePopup {
+        timer: Timer,
+        position: Vec3
+    }
+
+    /// Sent by `score_system` for the popup showing the points scored. Online only once the tick
+    /// they were scored in is certain, so a rollback doesn't show them twice or show some never
+    /// scored after all.
+    #[derive(Event, Clone)]
+    struct PointsScored {
+        text: String,
+        position: Vec3
+    }
+
+    #[derive(Event)]
+    struct ScoreEvent {
+        points: u64,
+        reason: ScoreReason,
+        position: Vec3,
+        // who earned the points, team bonuses only count towards the shared score
+        player: Option<PlayerId>,
+    }
+
+    #[derive(Component)]
+    struct ScoreCounterText;
+
+    /// Which player a ship, shot or kill belongs to.
+    #[derive(Component, Clone, Copy, Eq, PartialEq, Hash, Debug, Reflect)]
+    #[reflect(Component)]
+    enum PlayerId {
+        One,
+        Two,
+    }
+
+    impl PlayerId {
+        const ALL: [PlayerId; 2] = [PlayerId::One, PlayerId::Two];
+
+        fn index(self) -> usize {
+            self as usize
+        }
+
+        fn label(self) -> &'static str {
+            match self {
+                PlayerId::One => "1P",
+                PlayerId::Two => "2P",
+            }
+        }
+
+        fn keys(self) -> KeyBindings {
+            match self {
+                PlayerId::One => KeyBindings {
+                    left: KeyCode::ArrowLeft,
+                    right: KeyCode::ArrowRight,
+                    up: KeyCode::ArrowUp,
+                    down: KeyCode::ArrowDown,
+                    shoot: KeyCode::Space,
+                    bomb: KeyCode::KeyB,
+                },
+                PlayerId::Two => KeyBindings {
+                    left: KeyCode::KeyA,
+                    right: KeyCode::KeyD,
+                    up: KeyCode::KeyW,
+                    down: KeyCode::KeyS,
+                    shoot: KeyCode::KeyF,
+                    bomb: KeyCode::KeyG,
+                },
+            }
+        }
+
+        // the second ship is tinted so the two can be told apart
+        fn tint(self) -> Color {
+            match self {
+                PlayerId::One => Color::WHITE,
+                PlayerId::Two => Color::srgb(0.6, 0.85, 1.0),
+            }
+        }
+
+        // ships start and respawn side by side in co-op
+        fn spawn_position(self, players: PlayerCount) -> Vec3 {
+            match (players, self) {
+                (PlayerCount::One, _) => Vec3::ZERO,
+                (PlayerCount::Two, PlayerId::One) => Vec3::new(-PLAYER_SPAWN_SPACING, 0.0, 0.0),
+                (PlayerCount::Two, PlayerId::Two) => Vec3::new(PLAYER_SPAWN_SPACING, 0.0, 0.0),
+            }
+        }
+    }
+
+    struct KeyBindings {
+        left: KeyCode,
+        right: KeyCode,
+        up: KeyCode,
+        down: KeyCode,
+        shoot: KeyCode,
+        bomb: KeyCode
+    }
+
+    /// The gamepad each player steers with. Pads take the first free place as they connect and
+    /// keep it until they disconnect, so players never swap controllers mid-game.
+    #[derive(Resource, Default)]
+    struct GamepadAssignments([Option<Gamepad>; 2]);
+
+    fn gamepad_assignment_system(
+        mut events: EventReader<GamepadConnectionEvent>,
+        gamepads: Res<Gamepads>,
+        mut assignments: ResMut<GamepadAssignments>
+    ) {
+        if events.is_empty() {
+            return;
+        }
+        for event in events.read() {
+            if matches!(event.connection, GamepadConnection::Disconnected) {
+                for pad in &mut assignments.0 {
+                    if *pad == Some(event.gamepad) {
+                        *pad = None;
+                    }
+                }
+            }
+        }
+
+        // free places go to pads without one, which includes any left over while both were taken
+        let mut waiting: Vec<Gamepad> = gamepads
+            .iter()
+            .filter(|gamepad| !assignments.0.contains(&Some(*gamepad)))
+            .collect();
+        waiting.sort_by_key(|gamepad| gamepad.id);
+        let mut waiting = waiting.into_iter();
+        for pad in &mut assignments.0 {
+            if pad.is_none() {
+                *pad = waiting.next();
+            }
+        }
+    }
+
+    /// Keyboard and gamepad input of each player: their own keys, and the gamepad assigned to
+    /// them.
+    #[derive(SystemParam)]
+    struct PlayerInput<'w> {
+        keyboard: Res<'w, ButtonInput<KeyCode>>,
+        gamepads: Res<'w, GamepadAssignments>,
+        buttons: Res<'w, ButtonInput<GamepadButton>>,
+        axes: Res<'w, Axis<GamepadAxis>>,
+    }
+
+    impl PlayerInput<'_> {
+        fn gamepad(&self, id: PlayerId) -> Option<Gamepad> {
+            self.gamepads.0[id.index()]
+        }
+
+        fn button(&self, id: PlayerId, button: GamepadButtonType) -> Option<GamepadButton> {
+            self.gamepad(id).map(|gamepad| GamepadButton::new(gamepad, button))
+        }
+
+        /// Where the player is steering, each axis from -1.0 to 1.0.
+        fn movement(&self, id: PlayerId) -> Vec2 {
+            let keys = id.keys();
+            let axis = |negative, positive| {
+                (self.keyboard.pressed(positive) as i32 - self.keyboard.pressed(negative) as i32) as f32
+            };
+            let mut movement = Vec2::new(axis(keys.left, keys.right), axis(keys.down, keys.up));
+            if let Some(gamepad) = self.gamepad(id) {
+                let stick = Vec2::new(
+                    self.axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX)).unwrap_or(0.0),
+                    self.axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY)).unwrap_or(0.0),
+                );
+                let pad = |button| self.buttons.pressed(GamepadButton::new(gamepad, button)) as i32 as f32;
+                let dpad = Vec2::new(
+                    pad(GamepadButtonType::DPadRight) - pad(GamepadButtonType::DPadLeft),
+                    pad(GamepadButtonType::DPadUp) - pad(GamepadButtonType::DPadDown),
+                );
+                movement += stick + dpad;
+            }
+            movement.clamp(Vec2::NEG_ONE, Vec2::ONE)
+        }
+
+        fn shoot_held(&self, id: PlayerId) -> bool {
+            self.keyboard.pressed(id.keys().shoot)
+                || self.button(id, GamepadButtonType::South).is_some_and(|button| self.buttons.pressed(button))
+        }
+
+        fn bomb_pressed(&self, id: PlayerId) -> bool {
+            self.keyboard.just_pressed(id.keys().bomb)
+                || self.button(id, GamepadButtonType::East).is_some_and(|button| self.buttons.just_pressed(button))
+        }
+
+        /// What `id`'s controls have held down for this tick, with a bomb if one was asked for.
+        fn frame(&self, id: PlayerId, bomb: bool) -> InputFrame {
+            let movement = (self.movement(id) * 100.0).round();
+            InputFrame {
+                x: movement.x as i8,
+                y: movement.y as i8,
+                shoot: self.shoot_held(id),
+                bomb
+            }
+        }
+    }
+
+    /// One player's input for one tick, all the simulation knows of their controls. Small and
+    /// exact, so it can be sent over the network and compared with what was expected.
+    #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+    struct InputFrame {
+        // steering in hundredths, -100 to 100 on each axis
+        x: i8,
+        y: i8,
+        shoot: bool,
+        bomb: bool
+    }
+
+    impl InputFrame {
+        fn movement(&self) -> Vec2 {
+            Vec2::new(self.x as f32, self.y as f32) / 100.0
+        }
+    }
+
+    /// The input of every player for the tick being simulated, indexed by `PlayerId`.
+    #[derive(Resource, Default)]
+    struct TickInput(Vec<InputFrame>);
+
+    impl TickInput {
+        fn get(&self, id: PlayerId) -> InputFrame {
+            self.0.get(id.index()).copied().unwrap_or_default()
+        }
+    }
+
+    /// The lives, bombs and points of one player. Kept apart from the ship, which is gone once
+    /// its player is out of lives.
+    #[derive(Clone, Reflect)]
+    struct Pilot {
+        lives: u32,
+        bombs: u32,
+        score: u64
+    }
+
+    /// Every player of the run, indexed by `PlayerId`.
+    #[derive(Resource, Reflect)]
+    #[reflect(Resource)]
+    struct Pilots(Vec<Pilot>);
+
+    impl Pilots {
+        fn new(players: PlayerCount) -> Self {
+            Self(vec![Pilot { lives: STARTING_LIVES, bombs: STARTING_BOMBS, score: 0 }; players.players().len()])
+        }
+
+        fn get(&self, id: PlayerId) -> &Pilot {
+            &self.0[id.index()]
+        }
+
+        fn get_mut(&mut self, id: PlayerId) -> &mut Pilot {
+            &mut self.0[id.index()]
+        }
+
+        /// Takes a life from a player who was hit and tells whether they're still in the game.
+        /// With shared lives their last one is spared while their partner has some to give.
+        fn lose_life(&mut self, id: PlayerId, sharing: LifeSharing) -> bool {
+            let donor = self.0
+                .iter()
+                .position(|pilot| pilot.lives > 1)
+                .filter(|_| sharing == LifeSharing::Shared && self.get(id).lives == 1);
+            let pilot = match donor {
+                Some(index) => &mut self.0[index],
+                None => self.get_mut(id),
+            };
+            pilot.lives -= 1;
+            self.get(id).lives > 0
+        }
+    }
+
+    /// Set by input every frame and consumed when the next tick's input is read, so a tap is
+    /// neither lost nor repeated when a frame runs zero or several ticks.
+    #[derive(Resource, Default)]
+    struct BombRequested(Vec<PlayerId>);
+
+    /// Kills in quick succession build the chain, its multiplier applies to every kill's points.
+    #[derive(Resource, Clone, Reflect)]
+    #[reflect(Resource)]
+    struct Chain {
+        multiplier: u32,
+        kills: u32,
+        decay: Timer
+    }
+
+    impl Default for Chain {
+        fn default() -> Self {
+            Self {
+                multiplier: 1,
+                kills: 0,
+                decay: Timer::from_seconds(CHAIN_DECAY_SECONDS, TimerMode::Once)
+            }
+        }
+    }
+
+    impl Chain {
+        /// Counts a kill towards the chain and returns the multiplier it scores with.
+        fn register_kill(&mut self) -> u32 {
+            self.kills += 1;
+            self.multiplier = (1 + self.kills / CHAIN_KILLS_PER_STEP).min(CHAIN_MAX_MULTIPLIER);
+            self.decay.reset();
+            self.multiplier
+        }
+    }
+
+    /// What happened during the current wave, for the end-of-wave bonuses.
+    #[derive(Resource, Clone, Default, Reflect)]
+    #[reflect(Resource)]
+    struct WaveStats {
+        hits_taken: u32,
+        shots_fired: u32,
+        shots_hit: u32
+    }
+
+    #[derive(Resource, Clone, Reflect)]
+    #[reflect(Resource)]
+    struct Wave {
+        number: u32,
+        timer: Timer
+    }
+
+    /// How long the current run has lasted, shown and recorded in endless mode.
+    #[derive(Resource, Clone, Default, Reflect)]
+    #[reflect(Resource)]
+    struct Survival {
+        seconds: f32
+    }
+
+    /// Endless mode's mini-boss: the tier the next one arrives at and the one alive now.
+    #[derive(Resource, Clone, Reflect)]
+    #[reflect(Resource, MapEntitiesResource)]
+    struct MiniBosses {
+        next_tier: u32,
+        alive: Option<Entity>
+    }
+
+    impl MapEntities for MiniBosses {
+        fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+            self.alive = self.alive.map(|alive| entity_mapper.map_entity(alive));
+        }
+    }
+
+    #[derive(Component, Reflect)]
+    #[component(storage = "SparseSet")]
+    #[reflect(Component)]
+    struct MiniBoss;
+
+    #[derive(Clone, Reflect)]
+    struct ActivePowerUp {
+        name: String,
+        timer: Timer
+    }
+
+    /// Timed power-ups currently affecting the players, shown with their remaining time in the HUD.
+    #[derive(Resource, Default, Reflect)]
+    #[reflect(Resource)]
+    struct ActivePowerUps(Vec<ActivePowerUp>);
+
+    /// The player can't be killed while this runs, e.g. right after respawning.
+    #[derive(Component, Clone, Deref, DerefMut, Reflect)]
+    #[component(storage = "SparseSet")]
+    #[reflect(Component)]
+    struct Invulnerable(Timer);
+
+    #[derive(Component, Reflect)]
+    #[reflect(Component)]
+    struct SpawnTimer {
+        timer: f32
+    }
+
+    #[derive(Component)]
+    struct Explosion;
+
+    /// How particles look over their life and how they're thrown out. Sizes are in world units
+    /// and colours fade from the first to the second.
+    #[derive(Clone, Copy)]
+    struct ParticleEffect {
+        lifetime: f32,
+        speed: (f32, f32),
+        // half the angle of the cone around the direction particles are thrown in, in radians
+        spread: f32,
+        colors: (Color, Color),
+        sizes: (f32, f32)
+    }
+
+    const ENGINE_TRAIL: ParticleEffect = ParticleEffect {
+        lifetime: 0.3,
+        speed: (120.0, 200.0),
+        spread: 0.25,
+        colors: (Color::srgba(1.0, 0.85, 0.4, 1.0), Color::srgba(0.9, 0.2, 0.1, 0.0)),
+        sizes: (9.0, 3.0)
+    };
+
+    const HIT_SPARKS: ParticleEffect = ParticleEffect {
+        lifetime: 0.2,
+        speed: (250.0, 450.0),
+        spread: 0.8,
+        colors: (Color::srgba(1.0, 1.0, 0.8, 1.0), Color::srgba(1.0, 0.6, 0.2, 0.0)),
+        sizes: (6.0, 3.0)
+    };
+
+    const DEBRIS: ParticleEffect = ParticleEffect {
+        lifetime: 0.7,
+        speed: (80.0, 300.0),
+        spread: std::f32::consts::PI,
+        colors: (Color::srgba(0.8, 0.75, 0.7, 1.0), Color::srgba(0.3, 0.3, 0.3, 0.0)),
+        sizes: (9.0, 6.0)
+    };
+
+    const PICKUP_SPARKLE: ParticleEffect = ParticleEffect {
+        lifetime: 0.5,
+        speed: (20.0, 90.0),
+        spread: std::f32::consts::PI,
+        colors: (Color::srgba(1.0, 1.0, 1.0, 1.0), Color::srgba(0.6, 0.9, 1.0, 0.0)),
+        sizes: (6.0, 0.0)
+    };
+
+    /// Throws out particles continuously from an entity, like the trail behind the ship.
+    #[derive(Component)]
+    struct ParticleEmitter {
+        effect: ParticleEffect,
+        // particles per second
+        rate: f32,
+        direction: Vec2,
+        // from the entity's position, in world units
+        offset: Vec2,
+        accumulated: f32
+    }
+
+    impl ParticleEmitter {
+        fn new(effect: ParticleEffect, rate: f32, direction: Vec2, offset: Vec2) -> Self {
+            Self { effect, rate, direction, offset, accumulated: 0.0 }
+        }
+    }
+
+    /// A one-off spray of particles, sent from the simulation for hits, kills and pickups.
+    #[derive(Event)]
+    struct ParticleBurst {
+        effect: ParticleEffect,
+        position: Vec3,
+        direction: Vec2,
+        count: usize
+    }
+
+    /// Kicks the camera, freezes the game for a moment and flashes the screen, scaled down by the
+    /// effects display setting. Sent by the simulation for kills, player hits and bombs.
+    #[derive(Event, Clone, Copy, Default)]
+    struct Impact {
+        // added to the camera's trauma, capped at 1
+        trauma: f32,
+        // real seconds the game runs slowed down for
+        hit_stop: f32,
+        // starting opacity of a white flash over the screen
+        flash: f32
+    }
+
+    #[derive(Component, Default)]
+    struct CameraShake {
+        trauma: f32
+    }
+
+    #[derive(Resource, Default)]
+    struct HitStop {
+        remaining: f32
+    }
+
+    #[derive(Component)]
+    struct ScreenFlash;
+
+    #[derive(Component)]
+    struct Particle {
+        effect: ParticleEffect,
+        velocity: Vec2,
+        age: f32
+    }
+
+    #[derive(Clone, Copy, Eq, PartialEq, Debug, Deserialize, Reflect)]
+    enum EnemyKind {
+        Small,
+        Medium,
+        Big,
+    }
+
+    impl EnemyKind {
+        fn sheet(self) -> &'static str {
+            match self {
+                EnemyKind::Small => "enemy-small",
+                EnemyKind::Medium => "enemy-medium",
+                EnemyKind::Big => "enemy-big",
+            }
+        }
+
+        fn health(self) -> u32 {
+            match self {
+                EnemyKind::Small => 1,
+                EnemyKind::Medium => 2,
+                EnemyKind::Big => 4,
+            }
+        }
+
+        // half the size of the box shots and ships hit it within, in the sprite's pixels so a
+        // mini-boss drawn bigger is as big to hit
+        fn hit_half_extents(self) -> Vec2 {
+            match self {
+                EnemyKind::Small => Vec2::new(7.0, 6.0),
+                EnemyKind::Medium => Vec2::new(15.0, 5.0),
+                EnemyKind::Big => Vec2::new(15.0, 12.0),
+            }
+        }
+
+        fn hit_by(self, scale: Vec3, position: Vec3, other: Vec3) -> bool {
+            (position - other).truncate().abs().cmplt(self.hit_half_extents() * scale.truncate()).all()
+        }
+
+        // seconds between aimed shots, small enemies only ram
+        fn fire_interval(self) -> Option<f32> {
+            match self {
+                EnemyKind::Small => None,
+                EnemyKind::Medium => Some(2.5),
+                EnemyKind::Big => Some(1.5),
+            }
+        }
+
+        fn base_points(self) -> u64 {
+            match self {
+                EnemyKind::Small => 100,
+                EnemyKind::Medium => 250,
+                EnemyKind::Big => 500,
+            }
+        }
+
+        // how hard destroying one hits, only the big ones stop time
+        fn impact(self) -> Impact {
+            match self {
+                EnemyKind::Small => Impact { trauma: 0.15, ..default() },
+                EnemyKind::Medium => Impact { trauma: 0.25, ..default() },
+                EnemyKind::Big => Impact { trauma: 0.45, hit_stop: 0.08, ..default() },
+            }
+        }
+    }
+
+    #[derive(Component, Clone, Reflect)]
+    #[reflect(Component)]
+    struct Enemy {
+        kind: EnemyKind,
+        health: u32,
+        fire_timer: Option<Timer>,
+        is_hit: bool,
+        position: Vec3,
+        movement_speed: f32
+    }
+
+    #[derive(Component, Clone, Reflect)]
+    #[reflect(Component)]
+    struct EnemyBullet {
+        velocity: Vec2
+    }
+
+    /// Seconds until the player's weapon may fire again.
+    #[derive(Component, Clone, Reflect)]
+    #[reflect(Component)]
+    struct Cooldown {
+        remaining: f32
+    }
+
+    /// The player's gun, looked up in the weapon manifest every shot. `level` starts at 1, and
+    /// `charge` builds from 0 to 1 while fire is held for weapons that fire on release.
+    #[derive(Component, Clone, Reflect)]
+    #[reflect(Component)]
+    struct Weapon {
+        kind: WeaponKind,
+        level: usize,
+        charge: f32
+    }
+
+    #[derive(Component, Clone, Reflect)]
+    struct LaserSprite;
+
+    #[derive(Component, Clone, Reflect)]
+    #[reflect(Component, MapEntities)]
+    struct Laser {
+        movement_speed: f32,
+        laser_sprite: LaserSprite,
+        position: Vec3,
+        direction: Vec2,
+        damage: u32,
+        // piercing shots keep flying after a hit and damage each enemy they pass through once
+        pierce: bool,
+        homing: f32,
+        hits: Vec<Entity>,
+        owner: PlayerId
+    }
+
+    impl MapEntities for Laser {
+        fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+            for hit in &mut self.hits {
+                *hit = entity_mapper.map_entity(*hit);
+            }
+        }
+    }
+
+    #[derive(Component, Clone, Reflect)]
+    struct PlayerSprite;
+
+    #[derive(Component, Clone, Reflect)]
+    #[reflect(Component)]
+    struct Player {
+        movement_speed: f32,
+        player_sprite: PlayerSprite,
+        position: Vec3,
+        // -1.0 is banked hard left, 1.0 hard right
+        bank: f32
+    }
+
+    /// How often the gameplay ticks, kept in the save file for editing by hand.
+    #[derive(Resource, Clone, Serialize, Deserialize)]
+    #[serde(default)]
+    struct SimulationSettings {
+        tick_rate: f64
+    }
+
+    impl Default for SimulationSettings {
+        fn default() -> Self {
+            Self {
+                tick_rate: DEFAULT_TICK_RATE
+            }
+        }
+    }
+
+    impl SimulationSettings {
+        // a hand edited rate may be anything, including not a number
+        fn clamped(&self) -> Self {
+            Self {
+                tick_rate: match self.tick_rate {
+                    rate if rate.is_nan() => DEFAULT_TICK_RATE,
+                    rate => rate.clamp(MIN_TICK_RATE, MAX_TICK_RATE),
+                }
+            }
+        }
+    }
+
+    /// Position of a simulated entity as of the latest fixed tick. Gameplay reads and writes
+    /// this instead of `Transform`, which only holds the interpolated render position.
+    #[derive(Component, Default, Deref, DerefMut, Reflect)]
+    #[reflect(Component)]
+    struct PhysicalTranslation(Vec3);
+
+    /// Position as of the tick before, used to interpolate towards `PhysicalTranslation`.
+    #[derive(Component, Default, Deref, DerefMut, Reflect)]
+    #[reflect(Component)]
+    struct PreviousPhysicalTranslation(Vec3);
+
+    fn physical_translation(translation: Vec3) -> (PhysicalTranslation, PreviousPhysicalTranslation) {
+        (PhysicalTranslation(translation), PreviousPhysicalTranslation(translation))
+    }
+
+    fn apply_simulation_settings(
+        settings: Res<SimulationSettings>,
+        mut fixed_time: ResMut<Time<Fixed>>
+    ) {
+        fixed_time.set_timestep_hz(settings.tick_rate);
+    }
+
+    fn save_previous_physical_translation(
+        mut query: Query<(&PhysicalTranslation, &mut PreviousPhysicalTranslation)>
+    ) {
+        for (current, mut previous) in &mut query {
+            previous.0 = current.0;
+        }
+    }
+
+    fn interpolate_rendered_transform(
+        fixed_time: Res<Time<Fixed>>,
+        mut query: Query<(&mut Transform, &PhysicalTranslation, &PreviousPhysicalTranslation)>
+    ) {
+        let alpha = fixed_time.overstep_fraction();
+
+        for (mut transform, current, previous) in &mut query {
+            transform.translation = previous.lerp(current.0, alpha);
+        }
+    }
+
+    /// The area of the world that gameplay takes place in, centred on the origin. It is all of
+    /// the screen in the landscape layout and a narrow strip between HUD panels in portrait,
+    /// and nothing spawns, shoots or lingers outside it.
+    #[derive(Resource)]
+    struct Playfield {
+        rect: Rect
+    }
+
+    impl Playfield {
+        fn new(layout: Layout) -> Self {
+            let size = match layout {
+                Layout::Landscape => VIRTUAL_RESOLUTION,
+                Layout::Portrait => PORTRAIT_PLAYFIELD,
+            };
+            Self {
+                rect: Rect::from_center_size(Vec2::ZERO, size.as_vec2() * PIXEL_SCALE)
+            }
+        }
+
+        /// Maps playfield units, -1 to 1 from edge to edge, to world coordinates.
+        fn to_world(&self, point: Vec2) -> Vec2 {
+            self.rect.center() + point * self.rect.half_size()
+        }
+
+        // width of each HUD panel beside the playfield, in world units
+        fn side_panel_width(&self) -> f32 {
+            (screen_size().x - self.rect.width()) / 2.0
+        }
+    }
+
+    impl Default for Playfield {
+        fn default() -> Self {
+            Self::new(Layout::default())
+        }
+    }
+
+    // the whole virtual screen in world units
+    fn screen_size() -> Vec2 {
+        VIRTUAL_RESOLUTION.as_vec2() * PIXEL_SCALE
+    }
+
+    #[derive(Component)]
+    struct PlayfieldBackground;
+
+    fn apply_display_settings(
+        settings: Res<DisplaySettings>,
+        mut playfield: ResMut<Playfield>,
+        mut background_query: Query<&mut Sprite, With<PlayfieldBackground>>
+    ) {
+        *playfield = Playfield::new(settings.layout);
+        for mut sprite in &mut background_query {
+            sprite.custom_size = Some(playfield.rect.size());
+        }
+    }
+
+    #[derive(Component)]
+    struct MainCamera;
+
+    fn setup_camera(
+        mut commands: Commands,
+        playfield: Res<Playfield>
+    ) {
+        let mut camera = Camera2dBundle::default();
+        camera.projection.scaling_mode = ScalingMode::Fixed {
+            width: screen_size().x,
+            height: screen_size().y,
+        };
+        commands.spawn((camera, MainCamera, CameraShake::default()));
+
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: PLAYFIELD_BACKGROUND,
+                    custom_size: Some(playfield.rect.size()),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, -100.0),
+                ..default()
+            },
+            PlayfieldBackground,
+        ));
+    }
+
+    /// Shrinks the camera's viewport to the largest whole multiple of the virtual resolution
+    /// that fits the window, centred, so every art pixel covers the same number of screen
+    /// pixels. UI is scaled along with it and laid out inside the viewport.
+    fn fit_camera_to_window(
+        window_query: Query<&Window, With<PrimaryWindow>>,
+        mut camera_query: Query<&mut Camera, With<MainCamera>>,
+        mut ui_scale: ResMut<UiScale>
+    ) {
+        let (Ok(window), Ok(mut camera)) = (window_query.get_single(), camera_query.get_single_mut()) else {
+            return;
+        };
+        let window_size = window.physical_size();
+        // minimised
+        if window_size.x == 0 || window_size.y == 0 {
+            return;
+        }
+
+        let scale = (window_size / VIRTUAL_RESOLUTION).min_element();
+        let size = if scale > 0 {
+            VIRTUAL_RESOLUTION * scale
+        } else {
+            // a window smaller than the virtual resolution can't be pixel perfect, just fit it
+            let fit = (window_size.as_vec2() / VIRTUAL_RESOLUTION.as_vec2()).min_element();
+            (VIRTUAL_RESOLUTION.as_vec2() * fit).as_uvec2().max(UVec2::ONE)
+        };
+        let position = (window_size - size) / 2;
+
+        let unchanged = camera.viewport.as_ref().is_some_and(|viewport| {
+            viewport.physical_size == size && viewport.physical_position == position
+        });
+        if !unchanged {
+            camera.viewport = Some(Viewport {
+                physical_position: position,
+                physical_size: size,
+                ..default()
+            });
+        }
+
+        // one UI pixel per world unit, like the HUD was laid out for
+        let scale_factor = size.y as f32 / screen_size().y / window.scale_factor();
+        if ui_scale.0 != scale_factor {
+            ui_scale.0 = scale_factor;
+        }
+    }
+
+    /// Converts between the world and the HUD, which are scaled and offset differently.
+    #[derive(SystemParam)]
+    struct ScreenSpace<'w, 's> {
+        camera_query: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<MainCamera>>,
+        ui_scale: Res<'w, UiScale>,
+    }
+
+    impl ScreenSpace<'_, '_> {
+        /// Where a world position appears in UI units, from the top left of the playfield.
+        fn world_to_hud(&self, position: Vec3) -> Option<Vec2> {
+            let (camera, transform) = self.camera_query.get_single().ok()?;
+            camera.world_to_viewport(transform, position).map(|point| point / self.ui_scale.0)
+        }
+    }
+
+    fn setup(
+        mut commands: Commands,
+        sprites: Sprites,
+        difficulty: Res<Difficulty>,
+        coop: Res<CoopSettings>,
+        net: Option<Res<NetSession>>
+    ) {
+        let ship_sheet = sprites.sheet("ship");
+
+        for &id in coop.players.players() {
+            let animation_config_1 = ship_sheet.config("idle");
+            let position = id.spawn_position(coop.players);
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: id.tint(),
+                        ..default()
+                    },
+                    transform: Transform::from_scale(Vec3::splat(3.0))
+                        .with_translation(position),
+                    texture: ship_sheet.texture.clone(),
+                    ..default()
+                },
+                TextureAtlas {
+                    layout: ship_sheet.layout.clone(),
+                    index: animation_config_1.sprite_index(),
+                },
+                SpriteClip { sheet: "ship", clip: "idle" },
+                PlayerSprite,
+                NoFrustumCulling,
+                ParticleEmitter::new(ENGINE_TRAIL, 60.0, Vec2::NEG_Y, Vec2::new(0.0, -24.0)),
+                physical_translation(position),
+                Cooldown { remaining: 0.0 },
+                Weapon {
+                    kind: WeaponKind::Laser,
+                    level: 1,
+                    charge: 0.0
+                },
+                animation_config_1,
+                id,
+                Player {
+                    movement_speed: 500.0,
+                    player_sprite: PlayerSprite,
+                    position,
+                    bank: 0.0
+                }
+            ));
+        }
+
+        // online both games start from the host's seed
+        let seed = net.map_or_else(rand::random, |net| net.welcome.seed);
+        commands.insert_resource(RunInfo {
+            seed,
+            started_at: (now_as_u128() / 1000) as u64
+        });
+        commands.insert_resource(GameRng(ChaCha8Rng::seed_from_u64(seed)));
+        commands.insert_resource(Score::default());
+        commands.insert_resource(Pilots::new(coop.players));
+        commands.insert_resource(BombRequested::default());
+        commands.insert_resource(Chain::default());
+        commands.insert_resource(WaveStats::default());
+        commands.insert_resource(Wave {
+            number: 1,
+            timer: Timer::from_seconds(WAVE_DURATION_SECONDS, TimerMode::Repeating)
+        });
+        commands.insert_resource(ActivePowerUps::default());
+        commands.insert_resource(difficulty.challenge());
+        commands.insert_resource(Survival::default());
+        commands.insert_resource(MiniBosses {
+            next_tier: MINI_BOSS_EVERY_TIERS,
+            alive: None
+        });
+
+        commands.spawn(
+            SpawnTimer {
+                timer: 30.0
+            }
+        );
+    }
+
+    fn player_movement_system(
+        time: Res<Time>,
+        playfield: Res<Playfield>,
+        input: Res<TickInput>,
+        mut query: Query<(&mut Player, &PlayerId, &mut PhysicalTranslation, &mut SpriteClip)>,
+    ) {
+        for (mut ship, id, mut translation, mut sprite_clip) in &mut query {
+            let previous_x = translation.x;
+
+            let movement = input.get(*id).movement() * ship.movement_speed * time.delta_seconds();
+            translation.y += movement.y;
+            translation.x += movement.x;
+
+            translation.x = translation.x.clamp(playfield.rect.min.x + PLAYER_EDGE_INSET.x, playfield.rect.max.x - PLAYER_EDGE_INSET.x);
+            translation.y = translation.y.clamp(playfield.rect.min.y + PLAYER_EDGE_INSET.y, playfield.rect.max.y - PLAYER_EDGE_INSET.y);
+            ship.position.x = translation.x;
+            ship.position.y = translation.y;
+
+            // bank on the distance actually covered, so pushing against the edge of the screen stays level
+            let velocity_x = (translation.x - previous_x) / (ship.movement_speed * time.delta_seconds());
+            let easing = 1.0 - (-SHIP_BANK_EASING * time.delta_seconds()).exp();
+            ship.bank += (velocity_x.clamp(-1.0, 1.0) - ship.bank) * easing;
+
+            let column = ((ship.bank * 2.0).round() + 2.0) as usize;
+            let clip = SHIP_BANK_CLIPS[column.min(SHIP_BANK_CLIPS.len() - 1)];
+            if sprite_clip.clip != clip {
+                sprite_clip.clip = clip;
+            }
+        }
+    }
+
+    /// What the simulation tells the rest of the game when something is hit: points, kills, and
+    /// the sparks and shakes that show them.
+    #[derive(SystemParam)]
+    struct CombatEvents<'w> {
+        score: EventWriter<'w, ScoreEvent>,
+        destroyed: EventWriter<'w, EnemyDestroyed>,
+        bursts: EventWriter<'w, ParticleBurst>,
+        impacts: EventWriter<'w, Impact>,
+    }
+
+    impl CombatEvents<'_> {
+        /// Scores an enemy for the player who killed it and lets formations and waves know it is gone.
+        fn enemy_destroyed(&mut self, entity: Entity, enemy: &Enemy, position: Vec3, by: PlayerId) {
+            self.score.send(ScoreEvent {
+                points: enemy.kind.base_points(),
+                reason: ScoreReason::EnemyKilled,
+                position,
+                player: Some(by),
+            });
+            self.destroyed.send(EnemyDestroyed { entity, position, by });
+        }
+    }
+
+    /// Rolls what, if anything, a destroyed enemy leaves behind.
+    #[derive(SystemParam)]
+    struct PowerUpDrops<'w> {
+        sprites: Sprites<'w>,
+        weapons: Weapons<'w>,
+        rng: ResMut<'w, GameRng>,
+    }
+
+    impl PowerUpDrops<'_> {
+        fn roll(&mut self, commands: &mut Commands, position: Vec3) {
+            if !self.rng.gen_bool(POWER_UP_DROP_CHANCE) {
+                return;
+            }
+            if self.rng.gen_ratio(1, BOMB_POWER_UP_ODDS) {
+                spawn_power_up(commands, &self.sprites, PowerUpKind::Bomb, Color::WHITE, position);
+            } else if self.rng.gen_ratio(1, SHIELD_POWER_UP_ODDS) {
+                spawn_power_up(commands, &self.sprites, PowerUpKind::Shield, Color::WHITE, position);
+            } else {
+                let weapon = WeaponKind::ALL[self.rng.gen_range(0..WeaponKind::ALL.len())];
+                spawn_power_up(commands, &self.sprites, PowerUpKind::Weapon(weapon), self.weapons.definition(weapon).color(), position);
+            }
+        }
+    }
+
+    fn enemy_kill_system(
+        mut commands: Commands,
+        mut enemy_query: Query<(Entity, &PhysicalTranslation, &Transform, &mut Enemy)>,
+        mut drops: PowerUpDrops,
+        mut wave_stats: ResMut<WaveStats>,
+        mut shot_query: Query<(Entity, &PhysicalTranslation, &mut Laser)>,
+        mut events: CombatEvents,
+    ) {
+        if shot_query.is_empty() || enemy_query.is_empty() {
+            return;
+        }
+
+        for (shot_entity, shot_translation, mut shot) in &mut shot_query {
+            for (enemy_entity, enemy_translation, transform, mut enemy) in &mut enemy_query {
+                // a piercing shot may already have killed this enemy earlier in the tick
+                if enemy.health == 0 || shot.hits.contains(&enemy_entity) {
+                    continue;
+                }
+                if enemy.kind.hit_by(transform.scale, enemy_translation.0, shot_translation.0) {
+                    if shot.hits.is_empty() {
+                        wave_stats.shots_hit += 1;
+                    }
+                    shot.hits.push(enemy_entity);
+                    enemy.health = enemy.health.saturating_sub(shot.damage);
+                    events.bursts.send(ParticleBurst {
+                        effect: HIT_SPARKS,
+                        position: shot_translation.0,
+                        direction: -shot.direction,
+                        count: 6,
+                    });
+
+                    if enemy.health == 0 {
+                        events.enemy_destroyed(enemy_entity, &enemy, enemy_translation.0, shot.owner);
+                        events.impacts.send(enemy.kind.impact());
+
+                        commands.entity(enemy_entity).despawn();
+                        spawn_explosion(&mut commands, &drops.sprites, enemy_translation.0, 6.0);
+                        drops.roll(&mut commands, enemy_translation.0);
+                    }
+
+                    if !shot.pierce {
+                        commands.entity(shot_entity).despawn();
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn spawn_explosion(commands: &mut Commands, sprites: &Sprites, position: Vec3, scale: f32) {
+        let explosion_sheet = sprites.sheet("explosion");
+        let animation_config_1 = explosion_sheet.config("explode");
+
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform::from_scale(Vec3::splat(scale))
+                    .with_translation(position),
+                texture: explosion_sheet.texture.clone(),
+                ..default()
+            },
+            TextureAtlas {
+                layout: explosion_sheet.layout.clone(),
+                index: animation_config_1.sprite_index(),
+            },
+            SpriteClip { sheet: "explosion", clip: "explode" },
+            animation_config_1,
+            Explosion
+        ));
+    }
+
+    fn spawn_power_up(commands: &mut Commands, sprites: &Sprites, kind: PowerUpKind, color: Color, position: Vec3) {
+        let power_up_sheet = sprites.sheet("power-up");
+        let animation_config = power_up_sheet.config(kind.clip());
+
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    ..default()
+                },
+                transform: Transform::from_scale(Vec3::splat(3.0))
+                    .with_translation(position),
+                texture: power_up_sheet.texture.clone(),
+                ..default()
+            },
+            TextureAtlas {
+                layout: power_up_sheet.layout.clone(),
+                index: animation_config.sprite_index(),
+            },
+            SpriteClip { sheet: "power-up", clip: kind.clip() },
+            NoFrustumCulling,
+            physical_translation(position),
+            animation_config,
+            ParticleEmitter::new(PICKUP_SPARKLE, 12.0, Vec2::Y, Vec2::ZERO),
+            PowerUp { kind }
+        ));
+    }
+
+    fn power_up_movement_system(
+        time: Res<Time>,
+        mut query: Query<&mut PhysicalTranslation, With<PowerUp>>
+    ) {
+        for mut translation in &mut query {
+            translation.y -= POWER_UP_FALL_SPEED * time.delta_seconds();
+        }
+    }
+
+    /// Hands out shields, and keeps the active power-ups panel in step with them.
+    #[derive(SystemParam)]
+    struct Shields<'w, 's> {
+        active_power_ups: ResMut<'w, ActivePowerUps>,
+        coop: Res<'w, CoopSettings>,
+        // looked up by entity, an `Option<&Invulnerable>` in the player query would change the
+        // order players are found in
+        invulnerable_query: Query<'w, 's, &'static Invulnerable>,
+    }
+
+    impl Shields<'_, '_> {
+        fn grant(&mut self, commands: &mut Commands, player_entity: Entity, id: PlayerId) {
+            let invulnerable = self.invulnerable_query.get(player_entity).ok();
+            if invulnerable.is_none_or(|timer| timer.remaining_secs() < SHIELD_SECONDS) {
+                commands.entity(player_entity).insert(Invulnerable(Timer::from_seconds(SHIELD_SECONDS, TimerMode::Once)));
+            }
+            let name = match self.coop.players {
+                PlayerCount::One => "SHIELD".to_string(),
+                PlayerCount::Two => format!("{} SHIELD", id.label()),
+            };
+            // a second shield starts the first one over
+            let timer = Timer::from_seconds(SHIELD_SECONDS, TimerMode::Once);
+            match self.active_power_ups.0.iter_mut().find(|power_up| power_up.name == name) {
+                Some(power_up) => power_up.timer = timer,
+                None => self.active_power_ups.0.push(ActivePowerUp { name, timer }),
+            }
+        }
+    }
+
+    fn power_up_collect_system(
+        mut commands: Commands,
+        weapons: Weapons,
+        mut pilots: ResMut<Pilots>,
+        mut shields: Shields,
+        mut burst_events: EventWriter<ParticleBurst>,
+        mut player_query: Query<(Entity, &PhysicalTranslation, &mut Weapon, &PlayerId), With<Player>>,
+        power_up_query: Query<(Entity, &PhysicalTranslation, &PowerUp), Without<Player>>
+    ) {
+        for (entity, translation, power_up) in &power_up_query {
+            let collector = player_query
+                .iter_mut()
+                .find(|(_, player, _, _)| player.x.distance(translation.x) < 40.0 && player.y.distance(translation.y) < 40.0);
+            let Some((player_entity, _, mut weapon, id)) = collector else {
+                continue;
+            };
+            commands.entity(entity).despawn();
+            burst_events.send(ParticleBurst {
+                effect: PICKUP_SPARKLE,
+                position: translation.0,
+                direction: Vec2::Y,
+                count: 20,
+            });
+
+            match power_up.kind {
+                PowerUpKind::Weapon(kind) if kind == weapon.kind => {
+                    let max_level = weapons.definition(kind).levels.len();
+                    weapon.level = (weapon.level + 1).min(max_level);
+                }
+                PowerUpKind::Weapon(kind) => {
+                    weapon.kind = kind;
+                    weapon.charge = 0.0;
+                }
+                PowerUpKind::Bomb => {
+                    let pilot = pilots.get_mut(*id);
+                    pilot.bombs = (pilot.bombs + 1).min(MAX_BOMBS);
+                }
+                PowerUpKind::Shield => shields.grant(&mut commands, player_entity, *id),
+            }
+        }
+    }
+
+    fn player_weapons_system(
+        time: Res<Time>,
+        mut query: Query<(&mut Laser, &mut PhysicalTranslation)>,
+        enemy_query: Query<&PhysicalTranslation, (With<Enemy>, Without<Laser>)>
+    ) {
+        for (mut shot, mut translation) in query.iter_mut() {
+            if shot.homing > 0.0 {
+                let position = translation.truncate();
+                let target = enemy_query
+                    .iter()
+                    .map(|enemy| enemy.truncate())
+                    .min_by(|a, b| a.distance_squared(position).total_cmp(&b.distance_squared(position)));
+                if let Some(target) = target.filter(|target| *target != position) {
+                    let max_turn = shot.homing * time.delta_seconds();
+                    let turn = shot.direction.angle_between(target - position).clamp(-max_turn, max_turn);
+                    shot.direction = Vec2::from_angle(turn).rotate(shot.direction);
+                }
+            }
+
+            let movement = shot.direction * shot.movement_speed * time.delta_seconds();
+            translation.x += movement.x;
+            translation.y += movement.y;
+            shot.position = translation.0;
+        }
+
+    }
+
+    /// Turns homing shots to face the way the simulation steers them.
+    fn homing_rotation_system(
+        mut query: Query<(&Laser, &mut Transform)>
+    ) {
+        for (shot, mut transform) in &mut query {
+            if shot.homing > 0.0 {
+                transform.rotation = Quat::from_rotation_z(Vec2::Y.angle_between(shot.direction));
+            }
+        }
+    }
+    fn player_shoot_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        sprites: Sprites,
+        weapons: Weapons,
+        mut query: Query<(&mut Cooldown, &mut Weapon, &Player, &PlayerId)>,
+        input: Res<TickInput>,
+        mut wave_stats: ResMut<WaveStats>
+    ) {
+
+        for (mut cooldown, mut weapon, ship, id) in query.iter_mut() {
+            cooldown.remaining = (cooldown.remaining - time.delta_seconds()).max(0.0);
+            let definition = weapons.definition(weapon.kind);
+            let held = input.get(*id).shoot;
+
+            // charge weapons build up while fire is held and fire once it is let go
+            let charge = match &definition.charge {
+                Some(charge) if held => {
+                    weapon.charge = (weapon.charge + time.delta_seconds() / charge.seconds).min(1.0);
+                    continue;
+                }
+                Some(_) if weapon.charge == 0.0 => continue,
+                // let go too soon after the last shot, the charge fires once the cooldown is over
+                Some(_) if cooldown.remaining > 0.0 => continue,
+                Some(_) => std::mem::take(&mut weapon.charge),
+                None if held => 0.0,
+                None => continue,
+            };
+            if cooldown.remaining > 0.0 {
+                continue;
+            }
+            cooldown.remaining = definition.cooldown;
+
+            let level = definition.level(weapon.level);
+            let (damage_boost, scale_boost) = match &definition.charge {
+                Some(settings) => (1.0 + settings.damage_multiplier * charge, 1.0 + settings.scale_multiplier * charge),
+                None => (1.0, 1.0),
+            };
+            let laser_sheet = sprites.sheet("laser-bolts");
+            let count = level.projectiles.max(1);
+            for i in 0..count {
+                // -0.5 for the left-most projectile of the volley, 0.5 for the right-most
+                let side = if count == 1 { 0.0 } else { i as f32 / (count - 1) as f32 - 0.5 };
+                let position = Vec3::new(
+                    ship.position.x + side * level.spacing * (count - 1) as f32,
+                    ship.position.y + 6.0,
+                    0.0
+                );
+                let direction = Vec2::from_angle(-side * level.spread.to_radians()).rotate(Vec2::Y);
+                let animation_config_1 = laser_sheet.config("player-bolt");
+
+                wave_stats.shots_fired += 1;
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: definition.color(),
+                            ..default()
+                        },
+                        transform: Transform::from_scale(Vec3::splat(3.0 * scale_boost))
+                            .with_translation(position)
+                            .with_rotation(Quat::from_rotation_z(Vec2::Y.angle_between(direction))),
+                        texture: laser_sheet.texture.clone(),
+                        ..default()
+                    },
+                    TextureAtlas {
+                        layout: laser_sheet.layout.clone(),
+                        index: animation_config_1.sprite_index(),
+                    },
+                    SpriteClip { sheet: "laser-bolts", clip: "player-bolt" },
+                    LaserSprite,
+                    NoFrustumCulling,
+                    physical_translation(position),
+                    animation_config_1,
+                    Laser {
+                        movement_speed: definition.speed,
+                        laser_sprite: LaserSprite,
+                        position,
+                        direction,
+                        damage: (level.damage as f32 * damage_boost).round() as u32,
+                        pierce: definition.pierce,
+                        homing: definition.homing,
+                        hits: Vec::new(),
+                        owner: *id
+                    }
+                ));
+            }
+        }
+    }
+
+    fn now_as_u128() -> u128 {
+        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis()
+    }
+
+    /// The ship closest to `position`, the one enemies go for.
+    fn nearest_player(ship_query: &Query<&Player>, position: Vec2) -> Option<Vec2> {
+        ship_query
+            .iter()
+            .map(|ship| ship.position.truncate())
+            .min_by(|a, b| a.distance_squared(position).total_cmp(&b.distance_squared(position)))
+    }
+
+    /// What enemies and formations need to move along their patterns for a tick.
+    #[derive(SystemParam)]
+    struct Flight<'w, 's> {
+        time: Res<'w, Time>,
+        waves: WaveTable<'w>,
+        playfield: Res<'w, Playfield>,
+        ship_query: Query<'w, 's, &'static Player>,
+    }
+
+    impl Flight<'_, '_> {
+        /// Where `movement` takes something at `position` after this tick.
+        fn step(&self, movement: &mut EnemyMovement, position: Vec2, speed: f32) -> Vec2 {
+            let player = nearest_player(&self.ship_query, position);
+            movement.step(position, speed, self.time.delta_seconds(), self.waves.manifest(), &self.playfield, player)
+        }
+    }
+
+    /// Members of a formation that still hold their slot in it.
+    type HoldingFormation = (With<FormationMember>, Without<Formation>);
+
+    fn enemy_movement_system(
+        flight: Flight,
+        mut query: Query<(&mut Enemy, &mut EnemyMovement, &mut PhysicalTranslation)>
+    ) {
+        for (mut enemy, mut movement, mut translation) in &mut query {
+            let next = flight.step(&mut movement, translation.truncate(), enemy.movement_speed);
+
+            translation.x = next.x;
+            translation.y = next.y;
+            enemy.position = translation.0;
+        }
+    }
+
+    fn formation_movement_system(
+        mut commands: Commands,
+        flight: Flight,
+        mut rng: ResMut<GameRng>,
+        mut formation_query: Query<(&mut Formation, &mut EnemyMovement, &mut PhysicalTranslation), Without<Enemy>>,
+        mut member_query: Query<(&mut Enemy, &mut PhysicalTranslation), HoldingFormation>
+    ) {
+        let time = &flight.time;
+        for (mut formation, mut movement, mut anchor) in &mut formation_query {
+            let next = flight.step(&mut movement, anchor.truncate(), formation.speed);
+            anchor.x = next.x;
+            anchor.y = next.y;
+
+            // members still in formation close ranks over the slots of those lost
+            let holding: Vec<Entity> = formation.members
+                .iter()
+                .copied()
+                .filter(|member| member_query.contains(*member))
+                .collect();
+
+
+            let step = formation.speed * FORMATION_CATCH_UP * time.delta_seconds();
+            for (index, member) in holding.iter().enumerate() {
+                let slot = next + formation.shape.slot(index, holding.len(), formation.spacing, movement.elapsed);
+                let Ok((mut enemy, mut translation)) = member_query.get_mut(*member) else {
+                    continue;
+                };
+                let position = move_towards(translation.truncate(), slot, step);
+                translation.x = position.x;
+                translation.y = position.y;
+                enemy.position = translation.0;
+            }
+
+            // only dive at the player once the formation is in view
+            let in_view = flight.playfield.rect.contains(next);
+            if formation.break_timer.tick(time.delta()).just_finished() && in_view && !holding.is_empty() {
+                let member = holding[rng.gen_range(0..holding.len())];
+                let (_, translation) = member_query.get(member).expect("holding members are in the query");
+                commands.entity(member)
+                    .remove::<FormationMember>()
+                    .insert(EnemyMovement::new(MovementPattern::Kamikaze, translation.truncate(), false));
+            }
+        }
+    }
+
+    fn formation_status_system(
+        mut commands: Commands,
+        mut destroyed_events: EventReader<EnemyDestroyed>,
+        mut formation_query: Query<(Entity, &mut Formation)>,
+        enemy_query: Query<(), With<Enemy>>,
+        mut score_events: EventWriter<ScoreEvent>
+    ) {
+        let destroyed: Vec<&EnemyDestroyed> = destroyed_events.read().collect();
+
+        for (entity, mut formation) in &mut formation_query {
+            for event in &destroyed {
+                if formation.members.contains(&event.entity) {
+                    formation.killed += 1;
+                    formation.last_kill = event.position;
+                    formation.last_killer = Some(event.by);
+                }
+            }
+            formation.members.retain(|member| enemy_query.contains(*member));
+
+            if formation.members.is_empty() {
+                if formation.killed == formation.size {
+                    score_events.send(ScoreEvent {
+                        points: FORMATION_CLEAR_BONUS * formation.size as u64,
+                        reason: ScoreReason::FormationCleared,
+                        position: formation.last_kill,
+                        player: formation.last_killer,
+                    });
+                }
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    fn move_towards(from: Vec2, to: Vec2, step: f32) -> Vec2 {
+        let offset = to - from;
+        if offset.length() <= step {
+            to
+        } else {
+            from + offset.normalize() * step
+        }
+    }
+
+    fn despawn_finished_explosions(
+        mut commands: Commands,
+        mut finished_events: EventReader<AnimationFinished>,
+        query: Query<(), With<Explosion>>
+    ) {
+        for event in finished_events.read() {
+            if query.contains(event.entity) {
+                commands.entity(event.entity).despawn();
+            }
+        }
+    }
+
+    // particles are only for show, so they use their own randomness and leave replays alone
+    fn spawn_particles(commands: &mut Commands, budget: &mut usize, effect: ParticleEffect, position: Vec3, direction: Vec2, count: usize) {
+        let mut rng = rand::thread_rng();
+        let heading = direction.to_angle();
+        for _ in 0..count.min(*budget) {
+            let angle = heading + rng.gen_range(-effect.spread..=effect.spread);
+            let speed = rng.gen_range(effect.speed.0..=effect.speed.1);
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: effect.colors.0,
+                        custom_size: Some(Vec2::splat(effect.sizes.0)),
+                        ..default()
+                    },
+                    // just behind whatever they come from
+                    transform: Transform::from_translation(position - Vec3::Z),
+                    ..default()
+                },
+                Particle {
+                    effect,
+                    velocity: Vec2::from_angle(angle) * speed,
+                    age: 0.0
+                }
+            ));
+        }
+        *budget = budget.saturating_sub(count);
+    }
+
+    // how many more particles may be spawned before hitting the budget from display settings
+    fn particle_budget(settings: &DisplaySettings, particle_query: &Query<(), With<Particle>>) -> usize {
+        settings.particles.budget().saturating_sub(particle_query.iter().len())
+    }
+
+    fn particle_emitter_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        settings: Res<DisplaySettings>,
+        mut emitter_query: Query<(&mut ParticleEmitter, &Transform)>,
+        particle_query: Query<(), With<Particle>>
+    ) {
+        let mut budget = particle_budget(&settings, &particle_query);
+        for (mut emitter, transform) in &mut emitter_query {
+            emitter.accumulated += emitter.rate * time.delta_seconds();
+            let count = emitter.accumulated as usize;
+            emitter.accumulated -= count as f32;
+            let position = transform.translation + emitter.offset.extend(0.0);
+            spawn_particles(&mut commands, &mut budget, emitter.effect, position, emitter.direction, count);
+        }
+    }
+
+    fn enemy_debris_system(
+        mut destroyed_events: EventReader<EnemyDestroyed>,
+        mut burst_events: EventWriter<ParticleBurst>
+    ) {
+        for event in destroyed_events.read() {
+            burst_events.send(ParticleBurst {
+                effect: DEBRIS,
+                position: event.position,
+                direction: Vec2::Y,
+                count: 16,
+            });
+        }
+    }
+
+    fn particle_burst_system(
+        mut commands: Commands,
+        settings: Res<DisplaySettings>,
+        mut burst_events: EventReader<ParticleBurst>,
+        particle_query: Query<(), With<Particle>>
+    ) {
+        let mut budget = particle_budget(&settings, &particle_query);
+        for burst in burst_events.read() {
+            spawn_particles(&mut commands, &mut budget, burst.effect, burst.position, burst.direction, burst.count);
+        }
+    }
+
+    /// Moves particles and blends their colour and size over their lifetime.
+    fn particle_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>
+    ) {
+        for (entity, mut particle, mut transform, mut sprite) in &mut query {
+            particle.age += time.delta_seconds();
+            let t = particle.age / particle.effect.lifetime;
+            if t >= 1.0 {
+                commands.entity(entity).despawn();
+                continue;
+            }
+            transform.translation += (particle.velocity * time.delta_seconds()).extend(0.0);
+            let (start, end) = particle.effect.colors;
+            sprite.color = start.mix(&end, t);
+            let (start, end) = particle.effect.sizes;
+            sprite.custom_size = Some(Vec2::splat(start.lerp(end, t)));
+        }
+    }
+
+    /// The hit stop left to run, and the clocks it is counted in and slows down.
+    #[derive(SystemParam)]
+    struct HitStopClock<'w> {
+        real_time: Res<'w, Time<Real>>,
+        virtual_time: ResMut<'w, Time<Virtual>>,
+        hit_stop: ResMut<'w, HitStop>,
+    }
+
+    impl HitStopClock<'_> {
+        fn hold(&mut self, seconds: f32) {
+            self.hit_stop.remaining = self.hit_stop.remaining.max(seconds);
+        }
+
+        fn tick(&mut self) {
+            // counted in real time, since the virtual clock is the one being slowed
+            if self.hit_stop.remaining > 0.0 {
+                self.hit_stop.remaining -= self.real_time.delta_seconds();
+                self.virtual_time.set_relative_speed(HIT_STOP_SPEED);
+            } else if self.virtual_time.relative_speed() != 1.0 {
+                self.virtual_time.set_relative_speed(1.0);
+            }
+        }
+    }
+
+    fn impact_system(
+        mut commands: Commands,
+        mut impact_events: EventReader<Impact>,
+        settings: Res<DisplaySettings>,
+        mut clock: HitStopClock,
+        net: Option<Res<NetSession>>,
+        mut shake_query: Query<&mut CameraShake>
+    ) {
+        let scale = settings.effects.scale();
+        for impact in impact_events.read() {
+            for mut shake in &mut shake_query {
+                shake.trauma = (shake.trauma + impact.trauma * scale).min(1.0);
+            }
+            // online both games have to keep the same pace, so time never stops there
+            if net.is_none() {
+                clock.hold(impact.hit_stop * scale);
+            }
+            if impact.flash * scale > 0.0 {
+                commands.spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::srgba(1.0, 1.0, 1.0, impact.flash * scale).into(),
+                        z_index: ZIndex::Global(100),
+                        ..default()
+                    },
+                    ScreenFlash,
+                    OnGameScreen,
+                ));
+            }
+        }
+
+        clock.tick();
+    }
+
+    /// Offsets and tilts the camera by the square of its trauma, so small knocks barely show and
+    /// big ones rattle.
+    fn camera_shake_system(
+        real_time: Res<Time<Real>>,
+        mut query: Query<(&mut CameraShake, &mut Transform)>
+    ) {
+        for (mut shake, mut transform) in &mut query {
+            shake.trauma = (shake.trauma - TRAUMA_DECAY * real_time.delta_seconds()).max(0.0);
+            let amount = shake.trauma * shake.trauma;
+            let t = real_time.elapsed_seconds() * SHAKE_FREQUENCY;
+            // out of step sines stand in for noise
+            transform.translation.x = MAX_SHAKE_OFFSET * amount * (t * 1.1).sin();
+            transform.translation.y = MAX_SHAKE_OFFSET * amount * (t * 1.7 + 1.3).sin();
+            transform.rotation = Quat::from_rotation_z(MAX_SHAKE_ANGLE * amount * (t * 0.9 + 2.1).sin());
+        }
+    }
+
+    fn screen_flash_system(
+        mut commands: Commands,
+        real_time: Res<Time<Real>>,
+        mut query: Query<(Entity, &mut BackgroundColor), With<ScreenFlash>>
+    ) {
+        for (entity, mut background_color) in &mut query {
+            let alpha = background_color.0.alpha() - FLASH_FADE * real_time.delta_seconds();
+            if alpha <= 0.0 {
+                commands.entity(entity).despawn();
+            } else {
+                background_color.0.set_alpha(alpha);
+            }
+        }
+    }
+
+    /// What flies off the playfield and is despawned once it is out of sight.
+    type Culled = Or<(With<Laser>, With<EnemyBullet>, With<Enemy>, With<PowerUp>)>;
+
+    /// Despawns whatever flies too far out of the playfield. The margin leaves room for enemies
+    /// to spawn above the top edge and for paths that swing out and back in.
+    fn offscreen_culling_system(
+        mut commands: Commands,
+        playfield: Res<Playfield>,
+        query: Query<(Entity, &PhysicalTranslation), Culled>
+    ) {
+        let area = playfield.rect.inflate(PLAYFIELD_CULL_MARGIN);
+        for (entity, translation) in &query {
+            if !area.contains(translation.truncate()) {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    /// Bullets fired at the ships, kept apart from the ships' own translations.
+    type EnemyFire = (With<EnemyBullet>, Without<Player>);
+
+    /// Enemies and their bullets, whatever takes a life when it touches a ship.
+    #[derive(SystemParam)]
+    struct Hazards<'w, 's> {
+        enemy_query: Query<'w, 's, (Entity, &'static PhysicalTranslation, &'static Transform, &'static Enemy), Without<Player>>,
+        bullet_query: Query<'w, 's, (Entity, &'static PhysicalTranslation), EnemyFire>,
+    }
+
+    impl Hazards<'_, '_> {
+        /// The enemy ramming a ship at `position` or, failing that, a bullet hitting it.
+        fn hitting(&self, position: Vec3) -> Option<Entity> {
+            let rammed = self.enemy_query
+                .iter()
+                .find(|(_, translation, transform, enemy)| enemy.kind.hit_by(transform.scale, translation.0, position))
+                .map(|(entity, ..)| entity);
+            let shot = || self.bullet_query
+                .iter()
+                .find(|(_, bullet)| position.y.distance(bullet.y) < 15.5 && position.x.distance(bullet.x) < 15.5)
+                .map(|(entity, _)| entity);
+            rammed.or_else(shot)
+        }
+    }
+
+    /// Ships that can be hit, the ones not blinking after a respawn, bomb or shield.
+    type Vulnerable = (With<Player>, Without<Invulnerable>);
+
+    // losing a life reaches the pilots, the wave's stats, the ship and the screen all at once
+    #[allow(clippy::too_many_arguments)]
+    fn player_kill_system(
+        mut commands: Commands,
+        mut pilots: ResMut<Pilots>,
+        mut wave_stats: ResMut<WaveStats>,
+        coop: Res<CoopSettings>,
+        mut query: Query<(Entity, &PlayerId, &mut PhysicalTranslation, &mut PreviousPhysicalTranslation), Vulnerable>,
+        hazards: Hazards,
+        sprites: Sprites,
+        mut impact_events: EventWriter<Impact>
+    ) {
+        for (entity, id, mut player, mut previous) in &mut query {
+            let Some(hit_by) = hazards.hitting(player.0) else {
+                continue;
+            };
+
+            spawn_explosion(&mut commands, &sprites, player.0, 6.0);
+            impact_events.send(Impact { trauma: 0.6, hit_stop: 0.12, flash: 0.0 });
+            wave_stats.hits_taken += 1;
+            commands.entity(hit_by).despawn();
+
+            if pilots.lose_life(*id, coop.lives) {
+                // respawn in place of the old ship instead of rebuilding the entity
+                let spawn = id.spawn_position(coop.players);
+                commands.entity(entity).insert(Invulnerable(Timer::from_seconds(RESPAWN_INVULNERABILITY_SECONDS, TimerMode::Once)));
+                player.0 = spawn;
+                previous.0 = spawn;
+            } else {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    /// Ends the run once the last ship is gone, after `score_system` so whether it makes the high
+    /// scores counts the points of the tick it ended in.
+    fn run_over_system(
+        mut commands: Commands,
+        mut run_end: RunEnd,
+        checkpoint: Option<Res<Checkpoint>>,
+        ship_query: Query<(), With<Player>>
+    ) {
+        // the run goes on as long as anyone is still flying
+        if !ship_query.is_empty() {
+            return;
+        }
+
+        // one with a checkpoint to go back to may be continued first
+        if checkpoint.is_some() {
+            commands.insert_resource(ContinueCountdown(Timer::from_seconds(CONTINUE_SECONDS, TimerMode::Once)));
+        } else {
+            run_end.finish();
+        }
+    }
+
+    fn enemy_fire_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        sprites: Sprites,
+        playfield: Res<Playfield>,
+        challenge: Res<Challenge>,
+        mut query: Query<(&PhysicalTranslation, &mut Enemy)>,
+        ship_query: Query<&Player>
+    ) {
+        for (translation, mut enemy) in &mut query {
+            let Some(timer) = enemy.fire_timer.as_mut() else {
+                continue;
+            };
+            if !timer.tick(time.delta()).just_finished() {
+                continue;
+            }
+            // enemies still flying in from off screen hold their fire
+            if !playfield.rect.contains(translation.truncate()) {
+                continue;
+            }
+
+            let Some(player) = nearest_player(&ship_query, translation.truncate()) else {
+                continue;
+            };
+            let direction = (player - translation.truncate()).try_normalize().unwrap_or(Vec2::NEG_Y);
+            let laser_sheet = sprites.sheet("laser-bolts");
+            let animation_config = laser_sheet.config("enemy-bolt");
+
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform::from_scale(Vec3::splat(3.0))
+                        .with_translation(translation.0),
+                    texture: laser_sheet.texture.clone(),
+                    ..default()
+                },
+                TextureAtlas {
+                    layout: laser_sheet.layout.clone(),
+                    index: animation_config.sprite_index(),
+                },
+                SpriteClip { sheet: "laser-bolts", clip: "enemy-bolt" },
+                NoFrustumCulling,
+                physical_translation(translation.0),
+                animation_config,
+                EnemyBullet {
+                    velocity: direction * ENEMY_BULLET_SPEED * challenge.bullet_speed
+                }
+            ));
+        }
+    }
+
+    fn enemy_bullet_system(
+        time: Res<Time>,
+        mut query: Query<(&EnemyBullet, &mut PhysicalTranslation)>
+    ) {
+        for (bullet, mut translation) in &mut query {
+            let movement = bullet.velocity * time.delta_seconds();
+            translation.x += movement.x;
+            translation.y += movement.y;
+        }
+    }
+
+    fn bomb_input_system(
+        input: PlayerInput,
+        coop: Res<CoopSettings>,
+        mut requested: ResMut<BombRequested>
+    ) {
+        for &id in coop.players.players() {
+            if input.bomb_pressed(id) && !requested.0.contains(&id) {
+                requested.0.push(id);
+            }
+        }
+    }
+
+    /// Reads this tick's input of every player from their controls.
+    fn local_input_system(
+        input: PlayerInput,
+        coop: Res<CoopSettings>,
+        mut requested: ResMut<BombRequested>,
+        mut tick_input: ResMut<TickInput>
+    ) {
+        tick_input.0 = coop.players.players()
+            .iter()
+            .map(|&id| input.frame(id, requested.0.contains(&id)))
+            .collect();
+        requested.0.clear();
+    }
+
+    // online everyone plays with player one's controls, whichever ship is theirs
+    fn sample_local_input(
+        input: PlayerInput,
+        mut requested: ResMut<BombRequested>
+    ) -> InputFrame {
+        let frame = input.frame(PlayerId::One, requested.0.contains(&PlayerId::One));
+        requested.0.clear();
+        frame
+    }
+
+    // a bomb touches the bomber, every bullet and every enemy on screen at once
+    #[allow(clippy::too_many_arguments)]
+    fn bomb_system(
+        mut commands: Commands,
+        input: Res<TickInput>,
+        mut pilots: ResMut<Pilots>,
+        sprites: Sprites,
+        player_query: Query<(Entity, &PlayerId, &PhysicalTranslation, Option<&Invulnerable>), With<Player>>,
+        playfield: Res<Playfield>,
+        mut enemy_query: Query<(Entity, &PhysicalTranslation, &mut Enemy), Without<Player>>,
+        bullet_query: Query<Entity, With<EnemyBullet>>,
+        mut events: CombatEvents
+    ) {
+        for bomber in PlayerId::ALL.into_iter().filter(|&id| input.get(id).bomb) {
+            let Some((player_entity, _, player, invulnerable)) = player_query.iter().find(|(_, id, _, _)| **id == bomber) else {
+                continue;
+            };
+            let pilot = pilots.get_mut(bomber);
+            if pilot.bombs == 0 {
+                continue;
+            }
+            pilot.bombs -= 1;
+
+            for bullet in &bullet_query {
+                commands.entity(bullet).despawn();
+            }
+
+            for (enemy_entity, translation, mut enemy) in &mut enemy_query {
+                // the other player's bomb may already have destroyed it this tick
+                if enemy.health == 0 || !playfield.rect.contains(translation.truncate()) {
+                    continue;
+                }
+                enemy.health = enemy.health.saturating_sub(BOMB_DAMAGE);
+                if enemy.health == 0 {
+                    events.enemy_destroyed(enemy_entity, &enemy, translation.0, bomber);
+                    commands.entity(enemy_entity).despawn();
+                    spawn_explosion(&mut commands, &sprites, translation.0, 6.0);
+                }
+            }
+
+            spawn_explosion(&mut commands, &sprites, player.0, 40.0);
+            events.impacts.send(Impact { trauma: 0.8, hit_stop: 0.0, flash: 0.8 });
+
+            // don't cut a longer respawn invulnerability short
+            if invulnerable.is_none_or(|timer| timer.remaining_secs() < BOMB_INVULNERABILITY_SECONDS) {
+                commands.entity(player_entity).insert(Invulnerable(Timer::from_seconds(BOMB_INVULNERABILITY_SECONDS, TimerMode::Once)));
+            }
+        }
+    }
+
+    fn invulnerability_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut query: Query<(Entity, &mut Invulnerable)>
+    ) {
+        for (entity, mut invulnerable) in &mut query {
+            if invulnerable.tick(time.delta()).finished() {
+                commands.entity(entity).remove::<Invulnerable>();
+            }
+        }
+    }
+
+    fn invulnerability_blink_system(
+        mut query: Query<(&mut Visibility, Option<&Invulnerable>), With<Player>>
+    ) {
+        for (mut visibility, invulnerable) in &mut query {
+            let hidden = match invulnerable {
+                Some(timer) => (timer.elapsed_secs() * 10.0) as u32 % 2 == 1,
+                None => false,
+            };
+            let wanted = if hidden { Visibility::Hidden } else { Visibility::Inherited };
+            if *visibility != wanted {
+                *visibility = wanted;
+            }
+        }
+    }
+
+    /// What enemies are spawned with: their sprites, the playfield they enter, and how tough
+    /// the current challenge makes them.
+    #[derive(SystemParam)]
+    struct EnemySpawner<'w> {
+        sprites: Sprites<'w>,
+        playfield: Res<'w, Playfield>,
+        challenge: Res<'w, Challenge>,
+        rng: ResMut<'w, GameRng>,
+    }
+
+    impl EnemySpawner<'_> {
+        fn spawn(&mut self, commands: &mut Commands, kind: EnemyKind, position: Vec3) -> Entity {
+            let enemy_sheet = self.sprites.sheet(kind.sheet());
+            let animation_config_2 = enemy_sheet.config("fly");
+            let challenge = &self.challenge;
+
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform::from_scale(Vec3::splat(3.0))
+                        .with_translation(position),
+                    texture: enemy_sheet.texture.clone(),
+                    ..default()
+                },
+                TextureAtlas {
+                    layout: enemy_sheet.layout.clone(),
+                    index: animation_config_2.sprite_index(),
+                },
+                NoFrustumCulling,
+                physical_translation(position),
+                Enemy {
+                    kind,
+                    health: challenge.enemy_health(kind),
+                    fire_timer: kind.fire_interval().map(|interval| {
+                        let interval = interval / challenge.fire_rate;
+                        let mut timer = Timer::from_seconds(interval, TimerMode::Repeating);
+                        // stagger the first shot so enemies spawned close together don't fire in unison
+                        timer.set_elapsed(Duration::from_secs_f32(self.rng.gen_range(0.0..interval)));
+                        timer
+                    }),
+                    is_hit: false,
+                    position,
+                    movement_speed: ENEMY_MOVEMENT_SPEED * challenge.enemy_speed,
+                }
+            )).id()
+        }
+    }
+
+    fn enemy_spawn_system(
+        mut commands: Commands,
+        mut spawner: EnemySpawner,
+        waves: WaveTable,
+        wave: Res<Wave>,
+        query: Query<&SpawnTimer>
+    ) {
+        if query.is_empty() {
+            return;
+        }
+
+        let spawn_timer = query.single();
+        if spawn_timer.timer <= 1.0 {
+            let manifest = waves.manifest();
+            let playfield = &spawner.playfield;
+            let rng = &mut spawner.rng;
+            let entry = manifest.wave(wave.number).pick(&mut rng.0).clone();
+
+            let mirrored = entry.mirror && rng.gen_bool(0.5);
+            let side = if mirrored { -1.0 } else { 1.0 };
+            let (left, right) = (playfield.rect.min.x + ENEMY_EDGE_INSET, playfield.rect.max.x - ENEMY_EDGE_INSET);
+            let top = playfield.rect.max.y + ENEMY_SPAWN_HEIGHT;
+            let spawn = match &entry.pattern {
+                MovementPattern::Path(name) => manifest.path(name)
+                    .expect("the wave manifest loader checks every path a wave uses is defined")
+                    .point_at(0.0, playfield) * Vec2::new(side, 1.0),
+                MovementPattern::Hold { x, .. } => Vec2::new(playfield.to_world(Vec2::new(side * x, 0.0)).x.clamp(left, right), top),
+                _ => Vec2::new(rng.gen_range(left..right), top),
+            };
+            let movement = EnemyMovement::new(entry.pattern, spawn, mirrored);
+
+            let Some(definition) = entry.formation else {
+                let enemy = spawner.spawn(&mut commands, entry.kind, spawn.extend(0.0));
+                commands.entity(enemy).insert(movement);
+                return;
+            };
+
+            let challenge = *spawner.challenge;
+            let count = (definition.size + challenge.formation_bonus) as usize;
+            let members = (0..count)
+                .map(|index| {
+                    let position = spawn + definition.shape.slot(index, count, definition.spacing, 0.0);
+                    let enemy = spawner.spawn(&mut commands, entry.kind, position.extend(0.0));
+                    commands.entity(enemy).insert(FormationMember);
+                    enemy
+                })
+                .collect();
+
+            commands.spawn((
+                Formation {
+                    shape: definition.shape,
+                    spacing: definition.spacing,
+                    speed: ENEMY_MOVEMENT_SPEED * challenge.enemy_speed,
+                    size: count as u32,
+                    members,
+                    killed: 0,
+                    last_kill: spawn.extend(0.0),
+                    last_killer: None,
+                    break_timer: Timer::from_seconds(definition.break_seconds, TimerMode::Repeating),
+                },
+                movement,
+                physical_translation(spawn.extend(0.0)),
+            ));
+        }
+    }
+
+    fn spawn_timer_system(
+        challenge: Res<Challenge>,
+        mut query: Query<&mut SpawnTimer>
+    ) {
+        if query.is_empty() {
+            return;
+        }
+
+        let mut spawn_timer = query.single_mut();
+        spawn_timer.timer -= 1.0;
+        if spawn_timer.timer < 1.0 {
+            spawn_timer.timer = challenge.spawn_interval;
+        }
+    }
+
+    /// How the challenge ramps up with the waves, for the difficulty and mode being played.
+    #[derive(SystemParam)]
+    struct WaveRamp<'w> {
+        waves: WaveTable<'w>,
+        difficulty: Res<'w, Difficulty>,
+        mode: Res<'w, GameMode>,
+    }
+
+    impl WaveRamp<'_> {
+        fn challenge(&self, wave: u32) -> Challenge {
+            let ramp = self.mode.ramp_waves(wave, self.waves.manifest().waves.len() as u32);
+            self.difficulty.challenge().ramped(ramp)
+        }
+    }
+
+    fn wave_system(
+        time: Res<Time>,
+        ramp: WaveRamp,
+        mut wave: ResMut<Wave>,
+        mut wave_stats: ResMut<WaveStats>,
+        mut challenge: ResMut<Challenge>,
+        mut score_events: EventWriter<ScoreEvent>
+    ) {
+        if !wave.timer.tick(time.delta()).just_finished() {
+            return;
+        }
+
+        if wave_stats.hits_taken == 0 {
+            score_events.send(ScoreEvent {
+                points: NO_DAMAGE_WAVE_BONUS,
+                reason: ScoreReason::NoDamageWave,
+                position: Vec3::ZERO,
+                player: None,
+            });
+        }
+        if wave_stats.shots_fired >= ACCURACY_BONUS_MIN_SHOTS {
+            let hit = wave_stats.shots_hit.min(wave_stats.shots_fired) as u64;
+            score_events.send(ScoreEvent {
+                points: ACCURACY_BONUS * hit / wave_stats.shots_fired as u64,
+                reason: ScoreReason::Accuracy,
+                position: Vec3::new(0.0, -40.0, 0.0),
+                player: None,
+            });
+        }
+
+        wave.number += 1;
+        *wave_stats = WaveStats::default();
+        *challenge = ramp.challenge(wave.number);
+    }
+
+    fn survival_timer_system(
+        time: Res<Time>,
+        mut survival: ResMut<Survival>
+    ) {
+        survival.seconds += time.delta_seconds();
+    }
+
+    /// Sends endless mode's mini-boss in when its tier starts: a big enemy that holds near the
+    /// top of the playfield and fires quickly, tougher every time it returns.
+    fn mini_boss_spawn_system(
+        mut commands: Commands,
+        mut spawner: EnemySpawner,
+        mode: Res<GameMode>,
+        wave: Res<Wave>,
+        mut mini_bosses: ResMut<MiniBosses>
+    ) {
+        if *mode != GameMode::Endless || wave.number < mini_bosses.next_tier {
+            return;
+        }
+        let appearance = mini_bosses.next_tier / MINI_BOSS_EVERY_TIERS;
+        mini_bosses.next_tier += MINI_BOSS_EVERY_TIERS;
+
+        let position = Vec3::new(0.0, spawner.playfield.rect.max.y + ENEMY_SPAWN_HEIGHT, 0.0);
+        let boss = spawner.spawn(&mut commands, EnemyKind::Big, position);
+        let challenge = &spawner.challenge;
+        commands.entity(boss).insert((
+            Enemy {
+                kind: EnemyKind::Big,
+                health: (MINI_BOSS_HEALTH as f32 * appearance as f32 * challenge.enemy_health).round() as u32,
+                fire_timer: Some(Timer::from_seconds(MINI_BOSS_FIRE_INTERVAL / challenge.fire_rate, TimerMode::Repeating)),
+                is_hit: false,
+                position,
+                movement_speed: ENEMY_MOVEMENT_SPEED * challenge.enemy_speed,
+            },
+            EnemyMovement::new(MovementPattern::Hold { x: 0.0, y: 0.6 }, position.truncate(), false),
+            Transform::from_scale(Vec3::splat(MINI_BOSS_SCALE)).with_translation(position),
+            MiniBoss,
+        ));
+        mini_bosses.alive = Some(boss);
+    }
+
+    fn mini_boss_status_system(
+        mut destroyed_events: EventReader<EnemyDestroyed>,
+        mut mini_bosses: ResMut<MiniBosses>,
+        mut score_events: EventWriter<ScoreEvent>
+    ) {
+        for event in destroyed_events.read() {
+            if mini_bosses.alive != Some(event.entity) {
+                continue;
+            }
+            mini_bosses.alive = None;
+            score_events.send(ScoreEvent {
+                points: MINI_BOSS_BONUS,
+                reason: ScoreReason::MiniBoss,
+                position: event.position,
+                player: Some(event.by),
+            });
+        }
+    }
+
+    fn chain_decay_system(
+        time: Res<Time>,
+        mut chain: ResMut<Chain>
+    ) {
+        if chain.multiplier == 1 && chain.kills == 0 {
+            return;
+        }
+        if chain.decay.tick(time.delta()).just_finished() {
+            chain.multiplier = (chain.multiplier - 1).max(1);
+            chain.kills = (chain.multiplier - 1) * CHAIN_KILLS_PER_STEP;
+            chain.decay.reset();
+        }
+    }
+
+    fn power_up_timer_system(
+        time: Res<Time>,
+        mut power_ups: ResMut<ActivePowerUps>
+    ) {
+        // only touch the resource mutably while something is running, so the HUD
+        // doesn't rebuild the empty list every tick
+        if power_ups.0.is_empty() {
+            return;
+        }
+        for power_up in power_ups.0.iter_mut() {
+            power_up.timer.tick(time.delta());
+        }
+        power_ups.0.retain(|power_up| !power_up.timer.finished());
+    }
+
+    /// One tick of gameplay, run from `FixedUpdate`. A schedule of its own so a networked game
+    /// can roll back and run ticks again within a single fixed update.
+    #[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+    struct Simulation;
+
+    fn run_simulation(world: &mut World) {
+        world.run_schedule(Simulation);
+    }
+
+    /// What a `Snapshot` copies: every component and resource the simulation keeps its state in.
+    /// Each is registered for reflection in `main`.
+    #[derive(Resource)]
+    struct SnapshotFilter {
+        components: SceneFilter,
+        resources: SceneFilter,
+    }
+
+    impl Default for SnapshotFilter {
+        fn default() -> Self {
+            Self {
+                components: SceneFilter::deny_all()
+                    .allow::<PhysicalTranslation>()
+                    .allow::<PreviousPhysicalTranslation>()
+                    .allow::<Transform>()
+                    .allow::<Sprite>()
+                    .allow::<PlayerId>()
+                    .allow::<Player>()
+                    .allow::<Cooldown>()
+                    .allow::<Weapon>()
+                    .allow::<Invulnerable>()
+                    .allow::<Enemy>()
+                    .allow::<EnemyMovement>()
+                    .allow::<Formation>()
+                    .allow::<FormationMember>()
+                    .allow::<MiniBoss>()
+                    .allow::<Laser>()
+                    .allow::<EnemyBullet>()
+                    .allow::<PowerUp>()
+                    .allow::<SpawnTimer>(),
+                resources: SceneFilter::deny_all()
+                    .allow::<GameRng>()
+                    .allow::<RunInfo>()
+                    .allow::<Score>()
+                    .allow::<Pilots>()
+                    .allow::<Chain>()
+                    .allow::<WaveStats>()
+                    .allow::<Wave>()
+                    .allow::<Challenge>()
+                    .allow::<Survival>()
+                    .allow::<MiniBosses>()
+                    .allow::<ActivePowerUps>(),
+            }
+        }
+    }
+
+    /// The entities whose state is part of the simulation, unlike effects and the HUD. Their
+    /// sprites are spawned with `NoFrustumCulling`: otherwise the bounds culling needs are added
+    /// to them after the frame, which moves them to another table between the ticks of one frame
+    /// and the next but not between ticks run again after a rollback, and queries would visit
+    /// them in another order.
+    type Simulated = Or<(With<Player>, With<Enemy>, With<Formation>, With<Laser>, With<EnemyBullet>, With<PowerUp>, With<SpawnTimer>)>;
+
+    /// The simulation at one moment, copied through reflection: the `SnapshotFilter` resources and
+    /// every simulated entity with the `SnapshotFilter` components it has. Restoring it puts the
+    /// simulation back exactly, and it can be written out as RON and read back. Entities are
+    /// listed in the order queries visit them and put back in that order, since which enemy a
+    /// shot hits first depends on it.
+    struct Snapshot {
+        scene: DynamicScene,
+        // explosions, so those of ticks run again after a rollback don't show twice
+        effects: Vec<Entity>,
+    }
+
+    impl Snapshot {
+        fn serialize(&self, registry: &TypeRegistry) -> Result<String, String> {
+            self.scene.serialize(registry).map_err(|error| error.to_string())
+        }
+
+        fn deserialize(contents: &str, registry: &TypeRegistry) -> Result<Self, String> {
+            let mut deserializer = ron::Deserializer::from_str(contents).map_err(|error| error.to_string())?;
+            let scene = SceneDeserializer { type_registry: registry }
+                .deserialize(&mut deserializer)
+                .map_err(|error| error.to_string())?;
+            Ok(Self { scene, effects: Vec::new() })
+        }
+
+        fn resource<R: FromReflect>(&self) -> Option<R> {
+            self.scene.resources
+                .iter()
+                .filter(|resource| resource.get_represented_type_info().is_some_and(|info| info.type_id() == TypeId::of::<R>()))
+                .find_map(|resource| R::from_reflect(&**resource))
+        }
+
+        // how many played, going by its `Pilots` since a ship out of lives is gone
+        fn player_count(&self) -> Option<usize> {
+            self.resource::<Pilots>().map(|pilots| pilots.0.len())
+        }
+
+        /// A hash of the lives, bombs and points of every player and of where the generator is,
+        /// which two games playing out differently soon disagree on.
+        fn checksum(&self) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            if let Some(pilots) = self.resource::<Pilots>() {
+                for pilot in &pilots.0 {
+                    (pilot.lives, pilot.bombs, pilot.score).hash(&mut hasher);
+                }
+            }
+            self.resource::<Score>().map(|score| score.points).hash(&mut hasher);
+            if let Some(rng) = self.resource::<GameRng>() {
+                (rng.get_seed(), rng.get_stream(), rng.get_word_pos()).hash(&mut hasher);
+            }
+            hasher.finish()
+        }
+    }
+
+    /// What the simulation spawns only for show, left out of snapshots and cleared on a restore.
+    type Effect = With<Explosion>;
+
+    fn take_snapshot(
+        world: &World,
+        filter: Res<SnapshotFilter>,
+        registry: Res<AppTypeRegistry>,
+        simulated_query: Query<EntityRef, Simulated>,
+        effect_query: Query<Entity, Effect>
+    ) -> Snapshot {
+        let registry = registry.read();
+        let entities = simulated_query
+            .iter()
+            .map(|entity| DynamicEntity {
+                entity: entity.id(),
+                components: entity.archetype()
+                    .components()
+                    .filter_map(|id| world.components().get_info(id)?.type_id())
+                    .filter(|&type_id| filter.components.is_allowed_by_id(type_id))
+                    .filter_map(|type_id| registry.get_type_data::<ReflectComponent>(type_id)?.reflect(entity))
+                    .map(|component| component.clone_value())
+                    .collect(),
+            })
+            .collect();
+        let resources = registry
+            .iter()
+            .filter(|registration| filter.resources.is_allowed_by_id(registration.type_id()))
+            .filter_map(|registration| registration.data::<ReflectResource>()?.reflect(world))
+            .map(|resource| resource.clone_value())
+            .collect();
+
+        Snapshot {
+            scene: DynamicScene { resources, entities },
+            effects: effect_query.iter().collect(),
+        }
+    }
+
+    /// Puts the simulation back the way `snapshot` found it. Entities still around keep their
+    /// sprites and animations and only get their state back, those destroyed since are rebuilt
+    /// under new ids, and those spawned since are despawned.
+    fn restore_snapshot(world: &mut World, snapshot: &Snapshot) {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let filter = world.resource::<SnapshotFilter>().components.clone();
+        let registration = |value: &dyn Reflect| {
+            let registration = value.get_represented_type_info().and_then(|info| registry.get(info.type_id()));
+            if registration.is_none() {
+                error!("{} isn't registered for reflection, it can't be restored", value.reflect_type_path());
+            }
+            registration
+        };
+
+        let mut simulated_query = world.query_filtered::<Entity, Simulated>();
+        let mut ids = EntityHashMap::default();
+        let mut rebuilt = Vec::new();
+        for scene_entity in &snapshot.scene.entities {
+            let id = match simulated_query.get(world, scene_entity.entity) {
+                Ok(id) => id,
+                Err(_) => {
+                    let id = world.spawn_empty().id();
+                    rebuilt.push(id);
+                    id
+                }
+            };
+            ids.insert(scene_entity.entity, id);
+        }
+
+        let mut effect_query = world.query_filtered::<Entity, Effect>();
+        let gone: Vec<Entity> = simulated_query
+            .iter(world)
+            .filter(|entity| !ids.contains_key(entity))
+            .chain(effect_query.iter(world).filter(|entity| !snapshot.effects.contains(entity)))
+            .collect();
+        for entity in gone {
+            world.despawn(entity);
+        }
+
+        // taking the position off every entity empties their tables, putting it back in order
+        // below then refills them in the snapshot's order. Taken off one by one instead, each
+        // would move the last one of its table into its place and mix those already done up
+        for id in ids.values() {
+            world.entity_mut(*id).remove::<PhysicalTranslation>();
+        }
+        for scene_entity in &snapshot.scene.entities {
+            let kept: Vec<TypeId> = scene_entity.components
+                .iter()
+                .filter_map(|component| Some(component.get_represented_type_info()?.type_id()))
+                .collect();
+            let mut entity = world.entity_mut(ids[&scene_entity.entity]);
+            // what it only gained since, like the `Invulnerable` of a ship that respawned
+            let gained: Vec<&ReflectComponent> = entity.archetype()
+                .components()
+                .filter_map(|id| entity.world().components().get_info(id)?.type_id())
+                .filter(|type_id| filter.is_allowed_by_id(*type_id) && !kept.contains(type_id))
+                .filter_map(|type_id| registry.get_type_data::<ReflectComponent>(type_id))
+                .collect();
+            for reflect_component in gained {
+                reflect_component.remove(&mut entity);
+            }
+            for component in &scene_entity.components {
+                if let Some(reflect_component) = registration(&**component).and_then(|registration| registration.data::<ReflectComponent>()) {
+                    reflect_component.insert(&mut entity, &**component, &registry);
+                }
+            }
+            // in turn like the rest, the sprite moves the entity to another table
+            let id = entity.id();
+            if rebuilt.contains(&id) {
+                world.run_system_once_with(id, rebuild_simulated_sprite);
+            }
+        }
+
+        // the entities the restored components point at are the snapshot's, some under new ids now
+        let restored: Vec<Entity> = ids.values().copied().collect();
+        for registration in registry.iter().filter(|registration| filter.is_allowed_by_id(registration.type_id())) {
+            if let Some(map_entities) = registration.data::<ReflectMapEntities>() {
+                map_entities.map_entities(world, &mut ids, &restored);
+            }
+        }
+        for resource in &snapshot.scene.resources {
+            let Some(registration) = registration(&**resource) else {
+                continue;
+            };
+            if let Some(reflect_resource) = registration.data::<ReflectResource>() {
+                reflect_resource.insert(world, &**resource, &registry);
+            }
+            if let Some(map_entities) = registration.data::<ReflectMapEntitiesResource>() {
+                map_entities.map_entities(world, &mut ids);
+            }
+        }
+    }
+
+    /// What a restored entity's looks are rebuilt from.
+    type SimulatedSprite = (&'static Sprite, &'static Transform, Has<Player>, Option<&'static Enemy>, Has<Laser>, Option<&'static PowerUp>);
+
+    /// Gives an entity a restore brought back what the snapshot doesn't keep: its texture,
+    /// animation and particles.
+    fn rebuild_simulated_sprite(
+        In(entity): In<Entity>,
+        mut commands: Commands,
+        sprites: Sprites,
+        query: Query<SimulatedSprite>
+    ) {
+        // formations and the spawn timer have nothing to show
+        let Ok((sprite, transform, is_player, enemy, is_laser, power_up)) = query.get(entity) else {
+            return;
+        };
+        let mut entity = commands.entity(entity);
+        // the same components they were spawned with, anything else would put them in another table
+        if is_player {
+            entity.insert((
+                simulated_sprite(&sprites, "ship", "idle", sprite, transform),
+                SpriteClip { sheet: "ship", clip: "idle" },
+                PlayerSprite,
+                ParticleEmitter::new(ENGINE_TRAIL, 60.0, Vec2::NEG_Y, Vec2::new(0.0, -24.0)),
+            ));
+        } else if let Some(enemy) = enemy {
+            // enemies don't animate
+            let (sprite_bundle, texture_atlas, _, culling) = simulated_sprite(&sprites, enemy.kind.sheet(), "fly", sprite, transform);
+            entity.insert((sprite_bundle, texture_atlas, culling));
+        } else if is_laser {
+            entity.insert((
+                simulated_sprite(&sprites, "laser-bolts", "player-bolt", sprite, transform),
+                SpriteClip { sheet: "laser-bolts", clip: "player-bolt" },
+                LaserSprite,
+            ));
+        } else if let Some(power_up) = power_up {
+            entity.insert((
+                simulated_sprite(&sprites, "power-up", power_up.kind.clip(), sprite, transform),
+                SpriteClip { sheet: "power-up", clip: power_up.kind.clip() },
+                ParticleEmitter::new(PICKUP_SPARKLE, 12.0, Vec2::Y, Vec2::ZERO),
+            ));
+        } else {
+            entity.insert((
+                simulated_sprite(&sprites, "laser-bolts", "enemy-bolt", sprite, transform),
+                SpriteClip { sheet: "laser-bolts", clip: "enemy-bolt" },
+            ));
+        }
+    }
+
+    // the sprite of an entity a restore brings back, with the colour and transform it had
+    fn simulated_sprite(sprites: &Sprites, sheet: &str, clip: &str, sprite: &Sprite, transform: &Transform) -> (SpriteBundle, TextureAtlas, AnimationConfig, NoFrustumCulling) {
+        let sprite_sheet = sprites.sheet(sheet);
+        let animation_config = sprite_sheet.config(clip);
+        (
+            SpriteBundle {
+                sprite: sprite.clone(),
+                transform: *transform,
+                texture: sprite_sheet.texture.clone(),
+                ..default()
+            },
+            TextureAtlas {
+                layout: sprite_sheet.layout.clone(),
+                index: animation_config.sprite_index(),
+            },
+            animation_config,
+            NoFrustumCulling,
+        )
+    }
+
+    /// Puts back a snapshot read from a file. Entity ids in it may mean anything by then, so
+    /// every simulated entity is rebuilt.
+    fn restore_snapshot_from_file(world: &mut World, snapshot: &Snapshot) {
+        let mut simulated_query = world.query_filtered::<Entity, Simulated>();
+        let simulated: Vec<Entity> = simulated_query.iter(world).collect();
+        for entity in simulated {
+            world.despawn(entity);
+        }
+        restore_snapshot(world, snapshot);
+    }
+
+    /// What only debug builds have, the keys of `state_file_system`.
+    fn debug_keys_plugin(app: &mut App) {
+        if cfg!(debug_assertions) {
+            app.add_systems(Update, state_file_system.run_if(in_state(GameState::Game).and_then(not(resource_exists::<NetSession>))));
+        }
+    }
+
+    /// Debug keys: F5 writes the running game to a file beside the save file, F9 reads it back.
+    fn state_file_system(world: &mut World) {
+        let keyboard_input = world.resource::<ButtonInput<KeyCode>>();
+        let (save, load) = (keyboard_input.just_pressed(KeyCode::F5), keyboard_input.just_pressed(KeyCode::F9));
+        let path = SaveData::path().with_file_name(STATE_FILE_NAME);
+        let registry = world.resource::<AppTypeRegistry>().clone();
+
+        if save {
+            let snapshot = world.run_system_once(take_snapshot);
+            match snapshot.serialize(&registry.read()) {
+                Ok(contents) => write_data_file(&path, &contents),
+                Err(error) => error!("could not serialize the game state: {}", error),
+            }
+        }
+        if load {
+            let snapshot = fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|contents| Snapshot::deserialize(&contents, &registry.read()));
+            let players = world.resource::<CoopSettings>().players.players().len();
+            match snapshot {
+                // the HUD and the input have one place for each player of this run
+                Ok(snapshot) if snapshot.player_count() != Some(players) => warn!(
+                    "{} isn't of a game with {} players, it can't be loaded into this one",
+                    path.display(),
+                    players
+                ),
+                Ok(snapshot) => restore_snapshot_from_file(world, &snapshot),
+                Err(error) => error!("could not load the game state from {}: {}", path.display(), error),
+            }
+        }
+    }
+
+    /// A campaign run as it stood when its current wave began. It's written beside the save file
+    /// so the run can be continued after the game is closed, and kept for the continue offered
+    /// when the run is lost. Online and endless runs don't keep one.
+    #[derive(Resource, Serialize, Deserialize)]
+    struct Checkpoint {
+        // the settings the run is played under, which may have changed on the menus since
+        difficulty: Difficulty,
+        coop: CoopSettings,
+        layout: Layout,
+        wave: u32,
+        // the `Snapshot` as RON of its own, it can only be read back through the type registry
+        state: String
+    }
+
+    impl Checkpoint {
+        fn path() -> PathBuf {
+            SaveData::path().with_file_name(CHECKPOINT_FILE_NAME)
+        }
+
+        fn load() -> Result<Self, String> {
+            let contents = fs::read_to_string(Self::path()).map_err(|error| error.to_string())?;
+            ron::from_str(&contents).map_err(|error| error.to_string())
+        }
+
+        fn write(&self) {
+            match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+                Ok(contents) => write_data_file(&Self::path(), &contents),
+                Err(error) => error!("could not serialize the checkpoint: {}", error),
+            }
+        }
+
+        // once the run it's of is over
+        fn delete() {
+            let path = Self::path();
+            if let Err(error) = fs::remove_file(&path) {
+                if error.kind() != ErrorKind::NotFound {
+                    warn!("could not delete {}: {}", path.display(), error);
+                }
+            }
+        }
+    }
+
+    /// Saves a checkpoint whenever a wave starts.
+    fn checkpoint_system(world: &mut World) {
+        let wave = world.resource::<Wave>().number;
+        if world.get_resource::<Checkpoint>().is_some_and(|checkpoint| checkpoint.wave == wave) {
+            return;
+        }
+
+        let snapshot = world.run_system_once(take_snapshot);
+        let state = match snapshot.serialize(&world.resource::<AppTypeRegistry>().read()) {
+            Ok(state) => state,
+            Err(error) => {
+                error!("could not serialize the checkpoint: {}", error);
+                return;
+            }
+        };
+        let checkpoint = Checkpoint {
+            difficulty: *world.resource::<Difficulty>(),
+            coop: world.resource::<CoopSettings>().clone(),
+            layout: world.resource::<DisplaySettings>().layout,
+            wave,
+            state
+        };
+        checkpoint.write();
+        world.insert_resource(checkpoint);
+    }
+
+    /// Puts the checkpoint's run in place of the one just set up.
+    fn resume_checkpoint(world: &mut World) {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let snapshot = Snapshot::deserialize(&world.resource::<Checkpoint>().state, &registry.read());
+        match snapshot {
+            Ok(snapshot) => restore_snapshot_from_file(world, &snapshot),
+            Err(error) => error!("could not restore the checkpoint: {}", error),
+        }
+    }
+
+    /// The menu's continue: reads the checkpoint file and starts its run under its own settings.
+    fn load_checkpoint(world: &mut World) {
+        let checkpoint = match Checkpoint::load() {
+            Ok(checkpoint) => checkpoint,
+            Err(error) => {
+                error!("could not load the checkpoint from {}: {}", Checkpoint::path().display(), error);
+                return;
+            }
+        };
+
+        play_by(world, GameMode::Campaign, checkpoint.difficulty, checkpoint.coop.clone(), checkpoint.layout);
+        world.insert_resource(checkpoint);
+        // a networked run just finished may still be saying goodbye
+        world.remove_resource::<NetSession>();
+        world.resource_mut::<NextState<GameState>>().set(GameState::Game);
+        world.resource_mut::<NextState<MenuState>>().set(MenuState::Disabled);
+    }
+
+    /// Made-up lag added to every packet sent, for trying online play out on one machine.
+    #[derive(Component, Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+    enum SimulatedLag {
+        #[default]
+        Off,
+        Low,
+        Medium,
+        High,
+    }
+
+    impl SimulatedLag {
+        fn delay(self) -> Duration {
+            match self {
+                SimulatedLag::Off => Duration::ZERO,
+                SimulatedLag::Low => Duration::from_millis(50),
+                SimulatedLag::Medium => Duration::from_millis(100),
+                SimulatedLag::High => Duration::from_millis(200),
+            }
+        }
+    }
+
+    /// Made-up packet loss, the share of packets sent that are dropped instead.
+    #[derive(Component, Clone, Copy, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+    enum SimulatedLoss {
+        #[default]
+        Off,
+        Low,
+        Medium,
+        High,
+    }
+
+    impl SimulatedLoss {
+        fn chance(self) -> f64 {
+            match self {
+                SimulatedLoss::Off => 0.0,
+                SimulatedLoss::Low => 0.05,
+                SimulatedLoss::Medium => 0.1,
+                SimulatedLoss::High => 0.2,
+            }
+        }
+    }
+
+    /// The address to join and the test conditions for online play, set on the online screen.
+    #[derive(Resource, Clone, Serialize, Deserialize)]
+    struct NetSettings {
+        address: String,
+        #[serde(default)]
+        lag: SimulatedLag,
+        #[serde(default)]
+        loss: SimulatedLoss
+    }
+
+    impl NetSettings {
+        // hosting listens on the port of the address, so the same one works for both sides
+        fn port(&self) -> u16 {
+            self.address
+                .rsplit_once(':')
+                .and_then(|(_, port)| port.parse().ok())
+                .unwrap_or(NET_DEFAULT_PORT)
+        }
+    }
+
+    impl Default for NetSettings {
+        fn default() -> Self {
+            Self {
+                address: NET_DEFAULT_ADDRESS.to_string(),
+                lag: SimulatedLag::default(),
+                loss: SimulatedLoss::default()
+            }
+        }
+    }
+
+    impl SavedSettings for NetSettings {
+        fn store(&self, save_data: &mut SaveData) {
+            save_data.net = self.clone();
+        }
+    }
+
+    impl MenuOption for SimulatedLag {
+        type Settings = NetSettings;
+        const ALL: &'static [Self] = &[SimulatedLag::Off, SimulatedLag::Low, SimulatedLag::Medium, SimulatedLag::High];
+
+        fn label(self) -> &'static str {
+            match self {
+                SimulatedLag::Off => "Off",
+                SimulatedLag::Low => "50 ms",
+                SimulatedLag::Medium => "100 ms",
+                SimulatedLag::High => "200 ms",
+            }
+        }
+
+        fn get(settings: &NetSettings) -> Self {
+            settings.lag
+        }
+
+        fn set(self, settings: &mut NetSettings) {
+            settings.lag = self;
+        }
+    }
+
+    impl MenuOption for SimulatedLoss {
+        type Settings = NetSettings;
+        const ALL: &'static [Self] = &[SimulatedLoss::Off, SimulatedLoss::Low, SimulatedLoss::Medium, SimulatedLoss::High];
+
+        fn label(self) -> &'static str {
+            match self {
+                SimulatedLoss::Off => "Off",
+                SimulatedLoss::Low => "5%",
+                SimulatedLoss::Medium => "10%",
+                SimulatedLoss::High => "20%",
+            }
+        }
+
+        fn get(settings: &NetSettings) -> Self {
+            settings.loss
+        }
+
+        fn set(self, settings: &mut NetSettings) {
+            settings.loss = self;
+        }
+    }
+
+    /// What the host tells a joining player: everything both games have to agree on before the
+    /// first tick for them to play out the same.
+    #[derive(Clone, Serialize, Deserialize)]
+    struct Welcome {
+        seed: u64,
+        mode: GameMode,
+        difficulty: Difficulty,
+        lives: LifeSharing,
+        layout: Layout,
+        tick_rate: f64
+    }
+
+    /// The settings a host plays by, and sends a joining player in its `Welcome`.
+    #[derive(SystemParam)]
+    struct HostRules<'w> {
+        mode: Res<'w, GameMode>,
+        difficulty: Res<'w, Difficulty>,
+        coop: Res<'w, CoopSettings>,
+        display: Res<'w, DisplaySettings>,
+        simulation: Res<'w, SimulationSettings>,
+    }
+
+    impl HostRules<'_> {
+        fn welcome(&self, seed: u64) -> Welcome {
+            Welcome {
+                seed,
+                mode: *self.mode,
+                difficulty: *self.difficulty,
+                lives: self.coop.lives,
+                layout: self.display.layout,
+                tick_rate: self.simulation.tick_rate
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    enum Packet {
+        // a player asking to join, repeated until the host answers
+        Hello,
+        Welcome(Welcome),
+        // the sender's inputs from tick `first` on, how many of the receiver's it has, and the
+        // checksum of the last tick it settled
+        Inputs { first: u32, inputs: Vec<InputFrame>, received: u32, checksum: Option<(u32, u64)> },
+    }
+
+    impl Packet {
+        fn encode(&self) -> Option<Vec<u8>> {
+            match ron::to_string(self) {
+                Ok(text) => Some(text.into_bytes()),
+                Err(error) => {
+                    error!("could not encode packet: {}", error);
+                    None
+                }
+            }
+        }
+
+        fn decode(bytes: &[u8]) -> Option<Self> {
+            ron::de::from_bytes(bytes).ok()
+        }
+    }
+
+    /// The UDP socket to the other player. Packets sent go through the simulated lag and loss
+    /// of the network settings first.
+    struct NetLink {
+        socket: UdpSocket,
+        peer: SocketAddr,
+        lag: Duration,
+        loss: f64,
+        // packets held back by the simulated lag with when they're due, oldest first
+        delayed: VecDeque<(Instant, Vec<u8>)>,
+        last_heard: Instant
+    }
+
+    impl NetLink {
+        fn new(socket: UdpSocket, peer: SocketAddr, settings: &NetSettings) -> Self {
+            Self {
+                socket,
+                peer,
+                lag: settings.lag.delay(),
+                loss: settings.loss.chance(),
+                delayed: VecDeque::new(),
+                last_heard: Instant::now()
+            }
+        }
+
+        fn send(&mut self, packet: &Packet) {
+            let Some(bytes) = packet.encode() else {
+                return;
+            };
+            // made-up loss and lag aren't gameplay, so they don't touch the game's randomness
+            if rand::thread_rng().gen_bool(self.loss) {
+                return;
+            }
+            self.delayed.push_back((Instant::now() + self.lag, bytes));
+            self.flush();
+        }
+
+        // sends whatever the simulated lag has held back long enough
+        fn flush(&mut self) {
+            while self.delayed.front().is_some_and(|(due, _)| *due <= Instant::now()) {
+                let Some((_, bytes)) = self.delayed.pop_front() else {
+                    break;
+                };
+                if let Err(error) = self.socket.send_to(&bytes, self.peer) {
+                    warn!("could not send to {}: {}", self.peer, error);
+                }
+            }
+        }
+
+        /// Every packet from the other player since the last call.
+        fn receive(&mut self) -> Vec<Packet> {
+            self.flush();
+            let mut packets = Vec::new();
+            let mut buffer = [0; NET_MAX_PACKET_SIZE];
+            loop {
+                match self.socket.recv_from(&mut buffer) {
+                    Ok((size, from)) if from == self.peer => {
+                        if let Some(packet) = Packet::decode(&buffer[..size]) {
+                            self.last_heard = Instant::now();
+                            packets.push(packet);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                    // some systems report an unreachable peer on the next read, it's noticed by timing out
+                    Err(_) => break,
+                }
+            }
+            packets
+        }
+
+        fn timed_out(&self) -> bool {
+            self.last_heard.elapsed().as_secs_f32() > NET_TIMEOUT_SECONDS
+        }
+    }
+
+    /// Hosting or joining from the online screen, until the other player answers.
+    #[derive(Resource)]
+    struct NetLobby {
+        socket: UdpSocket,
+        // the host being joined, none while hosting
+        host: Option<SocketAddr>,
+        hello_timer: Timer
+    }
+
+    impl NetLobby {
+        fn host(settings: &NetSettings) -> Result<Self, String> {
+            let port = settings.port();
+            let socket = UdpSocket::bind(("0.0.0.0", port))
+                .and_then(|socket| socket.set_nonblocking(true).map(|_| socket))
+                .map_err(|error| {
+                    error!("could not host on port {}: {}", port, error);
+                    format!("Could not host on port {port}")
+                })?;
+            Ok(Self {
+                socket,
+                host: None,
+                hello_timer: Timer::from_seconds(NET_HELLO_INTERVAL_SECONDS, TimerMode::Repeating)
+            })
+        }
+
+        fn join(settings: &NetSettings) -> Result<Self, String> {
+            let host = settings.address
+                .to_socket_addrs()
+                .ok()
+                .and_then(|mut addresses| addresses.next())
+                .ok_or_else(|| format!("Could not find {}", settings.address))?;
+            let socket = UdpSocket::bind(("0.0.0.0", 0))
+                .and_then(|socket| socket.set_nonblocking(true).map(|_| socket))
+                .map_err(|error| {
+                    error!("could not open a socket to join {}: {}", host, error);
+                    format!("Could not join {host}")
+                })?;
+            let mut hello_timer = Timer::from_seconds(NET_HELLO_INTERVAL_SECONDS, TimerMode::Repeating);
+            // say hello on the first update instead of after a wait
+            hello_timer.set_elapsed(hello_timer.duration());
+            Ok(Self {
+                socket,
+                host: Some(host),
+                hello_timer
+            })
+        }
+
+        fn status(&self) -> String {
+            match self.host {
+                Some(host) => format!("Joining {host}"),
+                None => format!("Waiting for a player on port {}", self.socket.local_addr().map_or(0, |address| address.port())),
+            }
+        }
+    }
+
+    /// Inputs by tick, letting go of the oldest once no rollback can reach back to them.
+    #[derive(Default)]
+    struct InputLog {
+        // the tick of the first input still held
+        first: u32,
+        inputs: VecDeque<InputFrame>,
+    }
+
+    impl InputLog {
+        // one past the tick of the last input
+        fn end(&self) -> u32 {
+            self.first + self.inputs.len() as u32
+        }
+
+        fn get(&self, tick: u32) -> Option<InputFrame> {
+            tick.checked_sub(self.first).and_then(|index| self.inputs.get(index as usize)).copied()
+        }
+
+        fn last(&self) -> Option<InputFrame> {
+            self.inputs.back().copied()
+        }
+
+        fn push(&mut self, input: InputFrame) {
+            self.inputs.push_back(input);
+        }
+
+        // drops the inputs from `tick` on
+        fn truncate(&mut self, tick: u32) {
+            self.inputs.truncate(tick.saturating_sub(self.first) as usize);
+        }
+
+        // lets go of the inputs before `tick`
+        fn forget_before(&mut self, tick: u32) {
+            let count = (tick.saturating_sub(self.first) as usize).min(self.inputs.len());
+            self.inputs.drain(..count);
+            self.first += count as u32;
+        }
+
+        fn since(&self, tick: u32) -> impl Iterator<Item = InputFrame> + '_ {
+            self.inputs.iter().skip(tick.saturating_sub(self.first) as usize).copied()
+        }
+    }
+
+    /// A game played with someone over the network. Both games run the whole simulation on both
+    /// players' inputs: each sends the other its own, `NET_INPUT_DELAY` ticks ahead of using
+    /// them, and runs on with a guess at the other's when they're late. When they arrive and
+    /// differ from the guess, the game rolls back to the first tick guessed wrong and runs it
+    /// and every tick after again.
+    #[derive(Resource)]
+    struct NetSession {
+        link: NetLink,
+        local: PlayerId,
+        welcome: Welcome,
+        // the next tick to simulate
+        tick: u32,
+        // inputs by tick, ours known a few ticks ahead and theirs as far as received
+        local_inputs: InputLog,
+        remote_inputs: InputLog,
+        // what each tick simulated so far took the other player's input to be
+        assumed: InputLog,
+        // the state at the start of every tick that may still have to be run again
+        snapshots: VecDeque<(u32, Snapshot)>,
+        // how many of our inputs the other player has
+        acknowledged: u32,
+        // the first tick found to have been guessed wrong
+        rollback: Option<u32>,
+        // the tick the run ended in and where it went, held until that tick is certain
+        ending: Option<(u32, GameState)>,
+        // ours of the last settled ticks, and the other player's latest if it's of a tick not
+        // settled here yet
+        checksums: VecDeque<(u32, u64)>,
+        their_checksum: Option<(u32, u64)>,
+        // the first tick the two games were found to differ at
+        desync: Option<u32>,
+        // the points each tick not yet certain scored, shown once it is
+        scored: VecDeque<(u32, Vec<PointsScored>)>
+    }
+
+    impl NetSession {
+        fn new(link: NetLink, local: PlayerId, welcome: Welcome) -> Self {
+            Self {
+                link,
+                local,
+                welcome,
+                tick: 0,
+                local_inputs: InputLog {
+                    first: 0,
+                    inputs: VecDeque::from(vec![InputFrame::default(); NET_INPUT_DELAY as usize]),
+                },
+                remote_inputs: InputLog::default(),
+                assumed: InputLog::default(),
+                snapshots: VecDeque::new(),
+                acknowledged: 0,
+                rollback: None,
+                ending: None,
+                checksums: VecDeque::new(),
+                their_checksum: None,
+                desync: None,
+                scored: VecDeque::new()
+            }
+        }
+
+        // every tick before this one has the other player's real input
+        fn confirmed(&self) -> u32 {
+            self.remote_inputs.end()
+        }
+
+        /// Lets go of the snapshots and inputs of ticks no rollback can go back to any more: those
+        /// run on both players' real inputs, and our own the other player has. Returns the points
+        /// those ticks scored, which are certain now.
+        fn forget_settled(&mut self) -> Vec<PointsScored> {
+            let confirmed = self.confirmed();
+            while let Some((tick, snapshot)) = self.snapshots.pop_front() {
+                if tick >= confirmed {
+                    self.snapshots.push_front((tick, snapshot));
+                    break;
+                }
+                self.settle(tick, snapshot.checksum());
+            }
+            // the other player may be ahead, with inputs for ticks not run yet
+            let settled = confirmed.min(self.tick);
+            self.assumed.forget_before(settled);
+            // the last of theirs is still the guess at the next
+            self.remote_inputs.forget_before(settled.saturating_sub(1));
+            self.local_inputs.forget_before(settled.min(self.acknowledged));
+
+            let mut scored = Vec::new();
+            while self.scored.front().is_some_and(|(tick, _)| *tick < confirmed) {
+                scored.extend(self.scored.pop_front().into_iter().flat_map(|(_, points)| points));
+            }
+            scored
+        }
+
+        // keeps our checksum of a settled tick, checking it against theirs if it came first
+        fn settle(&mut self, tick: u32, checksum: u64) {
+            if self.checksums.len() == NET_CHECKSUM_HISTORY {
+                self.checksums.pop_front();
+            }
+            self.checksums.push_back((tick, checksum));
+            if let Some((their_tick, their_checksum)) = self.their_checksum.filter(|(their_tick, _)| *their_tick <= tick) {
+                self.their_checksum = None;
+                if their_tick == tick && their_checksum != checksum {
+                    self.differs_at(tick);
+                }
+            }
+        }
+
+        fn differs_at(&mut self, tick: u32) {
+            self.desync = Some(self.desync.map_or(tick, |desync| desync.min(tick)));
+        }
+
+        fn compare_checksum(&mut self, tick: u32, checksum: u64) {
+            match self.checksums.iter().find(|(ours, _)| *ours == tick) {
+                Some((_, ours)) if *ours != checksum => self.differs_at(tick),
+                Some(_) => {}
+                None if self.checksums.back().is_none_or(|(last, _)| *last < tick) => {
+                    self.their_checksum = Some((tick, checksum));
+                }
+                None => {}
+            }
+        }
+
+        /// The other player's input for `tick`, or a guess at it: the last one they sent, without
+        /// the bomb, which only ever lasts a tick.
+        fn remote_input(&self, tick: u32) -> InputFrame {
+            match self.remote_inputs.get(tick) {
+                Some(input) => input,
+                None => InputFrame {
+                    bomb: false,
+                    ..self.remote_inputs.last().unwrap_or_default()
+                },
+            }
+        }
+
+        // both players' input for `tick`, remembering what was assumed for the other player
+        fn tick_input(&mut self, tick: u32) -> Vec<InputFrame> {
+            let local = self.local_inputs.get(tick).expect("our inputs are kept until the ticks using them are settled");
+            let remote = self.remote_input(tick);
+            self.assumed.truncate(tick);
+            self.assumed.push(remote);
+            PlayerId::ALL
+                .iter()
+                .map(|&id| if id == self.local { local } else { remote })
+                .collect()
+        }
+
+        fn receive(&mut self) {
+            for packet in self.link.receive() {
+                match packet {
+                    // our welcome went missing, the joining player is still asking
+                    Packet::Hello if self.local == PlayerId::One => {
+                        let welcome = Packet::Welcome(self.welcome.clone());
+                        self.link.send(&welcome);
+                    }
+                    Packet::Inputs { first, inputs, received, checksum } => {
+                        self.acknowledged = self.acknowledged.max(received);
+                        if let Some((tick, checksum)) = checksum {
+                            self.compare_checksum(tick, checksum);
+                        }
+                        for (tick, input) in (first..).zip(inputs) {
+                            // only take them in order, whatever was lost is sent again
+                            if tick != self.confirmed() {
+                                continue;
+                            }
+                            if self.assumed.get(tick).is_some_and(|assumed| assumed != input) {
+                                self.rollback = Some(self.rollback.map_or(tick, |rollback| rollback.min(tick)));
+                            }
+                            self.remote_inputs.push(input);
+                        }
+                    }
+                    Packet::Hello | Packet::Welcome(_) => {}
+                }
+            }
+        }
+
+        // our inputs the other player hasn't got yet, with how many of theirs we have
+        fn send_inputs(&mut self) {
+            let first = self.acknowledged.min(self.local_inputs.end());
+            let inputs = self.local_inputs
+                .since(first)
+                .take(NET_MAX_INPUTS_PER_PACKET)
+                .collect();
+            let received = self.confirmed();
+            let checksum = self.checksums.back().copied();
+            self.link.send(&Packet::Inputs { first, inputs, received, checksum });
+        }
+    }
+
+    /// Waits for the other player: the host for someone to say hello, a joining player for the
+    /// host's welcome, saying hello again until it comes. Then both start the same game.
+    fn net_lobby_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut lobby: ResMut<NetLobby>,
+        settings: Res<NetSettings>,
+        rules: HostRules,
+        mut game_state: ResMut<NextState<GameState>>,
+        mut menu_state: ResMut<NextState<MenuState>>
+    ) {
+        if let Some(host) = lobby.host {
+            if lobby.hello_timer.tick(time.delta()).just_finished() {
+                if let Some(bytes) = Packet::Hello.encode() {
+                    if let Err(error) = lobby.socket.send_to(&bytes, host) {
+                        warn!("could not send to {}: {}", host, error);
+                    }
+                }
+            }
+        }
+
+        let mut buffer = [0; NET_MAX_PACKET_SIZE];
+        while let Ok((size, from)) = lobby.socket.recv_from(&mut buffer) {
+            let (welcome, local) = match (Packet::decode(&buffer[..size]), lobby.host) {
+                (Some(Packet::Hello), None) => (rules.welcome(rand::random()), PlayerId::One),
+                (Some(Packet::Welcome(welcome)), Some(host)) if from == host => (welcome, PlayerId::Two),
+                _ => continue,
+            };
+            let socket = match lobby.socket.try_clone() {
+                Ok(socket) => socket,
+                Err(error) => {
+                    error!("could not keep the connection to {}: {}", from, error);
+                    return;
+                }
+            };
+
+            let mut session = NetSession::new(NetLink::new(socket, from, &settings), local, welcome.clone());
+            if local == PlayerId::One {
+                session.link.send(&Packet::Welcome(welcome.clone()));
+            }
+            commands.insert_resource(session);
+            commands.remove_resource::<NetLobby>();
+
+            // play by the host's rules
+            commands.add(move |world: &mut World| {
+                let coop = CoopSettings { players: PlayerCount::Two, lives: welcome.lives };
+                play_by(world, welcome.mode, welcome.difficulty, coop, welcome.layout);
+                // ticks are only the same length on both sides at the same rate
+                world.insert_resource(SimulationSettings { tick_rate: welcome.tick_rate }.clamped());
+            });
+            game_state.set(GameState::Game);
+            menu_state.set(MenuState::Disabled);
+            return;
+        }
+    }
+
+    fn close_net_lobby(mut commands: Commands) {
+        commands.remove_resource::<NetLobby>();
+    }
+
+    /// Runs a networked game's simulation from `FixedUpdate`: rolls back and runs again the ticks
+    /// the other player's inputs turned out different for, then the next tick, unless it's too
+    /// far ahead of the other player already and has to wait for them.
+    fn net_simulation_system(world: &mut World) {
+        let lost = world.resource_scope(|world, mut session: Mut<NetSession>| {
+            session.receive();
+            if session.link.timed_out() {
+                warn!("lost the connection to {}", session.link.peer);
+                world.resource_mut::<NextState<GameState>>().set(GameState::Over);
+                return true;
+            }
+
+            if let Some(tick) = session.rollback.take() {
+                // the snapshots from there on are of ticks about to run again
+                let snapshot = session.snapshots
+                    .iter()
+                    .position(|(snapshot_tick, _)| *snapshot_tick == tick)
+                    .and_then(|index| session.snapshots.split_off(index).pop_front());
+                match snapshot {
+                    Some((_, snapshot)) => {
+                        restore_snapshot(world, &snapshot);
+                        session.scored.retain(|(scored_tick, _)| *scored_tick < tick);
+                        if session.ending.is_some_and(|(ending, _)| ending >= tick) {
+                            session.ending = None;
+                        }
+                        // these ticks shook the screen and threw their particles when they first
+                        // ran, what they send again goes to events nothing reads
+                        let impacts = world.remove_resource::<Events<Impact>>();
+                        let bursts = world.remove_resource::<Events<ParticleBurst>>();
+                        world.init_resource::<Events<Impact>>();
+                        world.init_resource::<Events<ParticleBurst>>();
+                        for tick in tick..session.tick {
+                            simulate_tick(world, &mut session, tick);
+                        }
+                        if let Some(impacts) = impacts {
+                            world.insert_resource(impacts);
+                        }
+                        if let Some(bursts) = bursts {
+                            world.insert_resource(bursts);
+                        }
+                    }
+                    None => error!("no snapshot to roll back to tick {}, the games may differ", tick),
+                }
+            }
+            let scored = session.forget_settled();
+            world.send_event_batch(scored);
+            if let Some(tick) = session.desync {
+                error!("the game with {} played out differently from tick {}", session.link.peer, tick);
+                world.resource_mut::<NextState<GameState>>().set(GameState::Over);
+                return true;
+            }
+
+            if session.tick < session.confirmed() + NET_MAX_PREDICTION {
+                let input = world.run_system_once(sample_local_input);
+                session.local_inputs.push(input);
+                let tick = session.tick;
+                simulate_tick(world, &mut session, tick);
+                session.tick += 1;
+            }
+            if let Some((tick, state)) = session.ending {
+                if tick < session.confirmed() {
+                    world.resource_mut::<NextState<GameState>>().set(state);
+                }
+            }
+
+            session.send_inputs();
+            false
+        });
+
+        if lost {
+            world.remove_resource::<NetSession>();
+        }
+    }
+
+    fn simulate_tick(world: &mut World, session: &mut NetSession, tick: u32) {
+        let snapshot = world.run_system_once(take_snapshot);
+        session.snapshots.push_back((tick, snapshot));
+        world.resource_mut::<TickInput>().0 = session.tick_input(tick);
+        // the points of the tick are held back from the popups until it is certain, apart from
+        // those of ticks already certain the popups haven't read yet
+        let certain = world.remove_resource::<Events<PointsScored>>();
+        world.init_resource::<Events<PointsScored>>();
+        world.run_schedule(Simulation);
+        let scored = world.resource_mut::<Events<PointsScored>>().drain().collect();
+        session.scored.push_back((tick, scored));
+        if let Some(certain) = certain {
+            world.insert_resource(certain);
+        }
+
+        // a run ending on a guess may go on after all, so it's held until the tick is certain
+        let mut next_state = world.resource_mut::<NextState<GameState>>();
+        if let NextState::Pending(state) = *next_state {
+            session.ending.get_or_insert((tick, state));
+            next_state.reset();
+        }
+    }
+
+    /// Keeps in touch after a networked run ends, until the other player has every input they
+    /// need to end it too.
+    fn net_linger_system(
+        mut commands: Commands,
+        mut session: ResMut<NetSession>
+    ) {
+        session.receive();
+        session.send_inputs();
+        if session.acknowledged >= session.local_inputs.end() || session.link.timed_out() {
+            commands.remove_resource::<NetSession>();
+        }
+    }
+
+    #[derive(Component)]
+    struct HudLivesText(PlayerId);
+
+    #[derive(Component)]
+    struct HudPlayerScoreText(PlayerId);
+
+    #[derive(Component)]
+    struct HudSurvivalText;
+
+    #[derive(Component)]
+    struct HudBombsText(PlayerId);
+
+    #[derive(Component)]
+    struct HudMultiplierText;
+
+    #[derive(Component)]
+    struct HudWaveText;
+
+    #[derive(Component)]
+    struct HudWeaponText(PlayerId);
+
+    #[derive(Component)]
+    struct HudPowerUpsText;
+
+    // the pixel font's line is 10 of its pixels tall, so each one is drawn 2 by 2
+    const HUD_FONT_SIZE: f32 = 20.0;
+    const HUD_LABEL_COLOR: Color = Color::srgb(0.65, 0.65, 0.65);
+
+    impl GameAssets {
+        // in the pixel font, crisp at whole multiples of 10
+        fn text_style(&self, font_size: f32, color: Color) -> TextStyle {
+            TextStyle {
+                font: self.font.clone(),
+                font_size,
+                color,
+            }
+        }
+
+        fn hud_text_style(&self, color: Color) -> TextStyle {
+            self.text_style(HUD_FONT_SIZE, color)
+        }
+    }
+
+    fn format_score(score: u64) -> String {
+        format!("{:08}", score)
+    }
+
+    fn spawn_hud_value(parent: &mut ChildBuilder, game_assets: &GameAssets, label: &str, value: String, marker: impl Bundle) {
+        parent.spawn((
+            TextBundle::from_sections([
+                TextSection::new(format!("{label} "), game_assets.hud_text_style(HUD_LABEL_COLOR)),
+                TextSection::new(value, game_assets.hud_text_style(WHITE.into())),
+            ]),
+            marker,
+        ));
+    }
+
+    /// One HUD value per player, labelled with whose it is once there's more than one.
+    fn spawn_player_hud_values<M: Component>(
+        parent: &mut ChildBuilder,
+        game_assets: &GameAssets,
+        players: PlayerCount,
+        label: &str,
+        value: impl Fn(PlayerId) -> String,
+        marker: impl Fn(PlayerId) -> M
+    ) {
+        for &id in players.players() {
+            let label = match players {
+                PlayerCount::One => label.to_string(),
+                PlayerCount::Two => format!("{} {label}", id.label()),
+            };
+            spawn_hud_value(parent, game_assets, &label, value(id), marker(id));
+        }
+    }
+
+    fn hud_setup(
+        mut commands: Commands,
+        settings: Res<DisplaySettings>,
+        playfield: Res<Playfield>,
+        mode: Res<GameMode>,
+        coop: Res<CoopSettings>,
+        game_assets: Res<GameAssets>
+    ) {
+        let column = |align_items| NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items,
+                ..default()
+            },
+            ..default()
+        };
+        let controls = || TextBundle::from_section(
+            match coop.players {
+                PlayerCount::One => "Move: Arrow Keys\nShoot: Space (hold to charge the beam)\nBomb: B",
+                PlayerCount::Two => "1P: Arrow Keys, Space, B\n2P: WASD, F, G\nGamepads: Stick, A, B\nHold shoot to charge the beam",
+            },
+            game_assets.hud_text_style(HUD_LABEL_COLOR),
+        );
+        let players = coop.players;
+        let power_ups = |justify| (
+            TextBundle::from_section("", game_assets.hud_text_style(WHITE.into())).with_text_justify(justify),
+            HudPowerUpsText,
+        );
+
+        if settings.layout == Layout::Portrait {
+            // the playfield is a strip in the middle, the HUD sits in solid panels either side of it
+            let panel = |align_items| NodeBundle {
+                style: Style {
+                    width: Val::Px(playfield.side_panel_width()),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items,
+                    padding: UiRect::all(Val::Px(12.0)),
+                    ..default()
+                },
+                background_color: HUD_PANEL_COLOR.into(),
+                ..default()
+            };
+            commands
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            justify_content: JustifyContent::SpaceBetween,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    OnGameScreen,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(panel(AlignItems::FlexStart)).with_children(|parent| {
+                        parent.spawn(column(AlignItems::FlexStart)).with_children(|parent| {
+                            spawn_hud_value(parent, &game_assets, "SCORE", format_score(0), ScoreCounterText);
+                            if players == PlayerCount::Two {
+                                spawn_player_hud_values(parent, &game_assets, players, "SCORE", |_| format_score(0), HudPlayerScoreText);
+                            }
+                            spawn_hud_value(parent, &game_assets, "CHAIN", "x1".to_string(), HudMultiplierText);
+                            spawn_player_hud_values(parent, &game_assets, players, "WEAPON", |_| String::new(), HudWeaponText);
+                        });
+                        parent.spawn(controls());
+                    });
+                    parent.spawn(panel(AlignItems::FlexEnd)).with_children(|parent| {
+                        parent.spawn(column(AlignItems::FlexEnd)).with_children(|parent| {
+                            spawn_hud_value(parent, &game_assets, mode.wave_label(), "01".to_string(), HudWaveText);
+                            if *mode == GameMode::Endless {
+                                spawn_hud_value(parent, &game_assets, "TIME", format_duration(0), HudSurvivalText);
+                            }
+                            spawn_player_hud_values(parent, &game_assets, players, "LIVES", |_| STARTING_LIVES.to_string(), HudLivesText);
+                            spawn_player_hud_values(parent, &game_assets, players, "BOMBS", |_| STARTING_BOMBS.to_string(), HudBombsText);
+                        });
+                        parent.spawn(power_ups(JustifyText::Right));
+                    });
+                });
+            return;
+        }
+
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        flex_direction: FlexDirection::Column,
+                        justify_content: JustifyContent::SpaceBetween,
+                        padding: UiRect::all(Val::Px(12.0)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                OnGameScreen,
+            ))
+            .with_children(|parent| {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            justify_content: JustifyContent::SpaceBetween,
+                            align_items: AlignItems::FlexStart,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(column(AlignItems::FlexStart)).with_children(|parent| {
+                            spawn_hud_value(parent, &game_assets, "SCORE", format_score(0), ScoreCounterText);
+                            if players == PlayerCount::Two {
+                                spawn_player_hud_values(parent, &game_assets, players, "SCORE", |_| format_score(0), HudPlayerScoreText);
+                            }
+                            spawn_hud_value(parent, &game_assets, "CHAIN", "x1".to_string(), HudMultiplierText);
+                            spawn_player_hud_values(parent, &game_assets, players, "WEAPON", |_| String::new(), HudWeaponText);
+                        });
+                        parent.spawn(column(AlignItems::Center)).with_children(|parent| {
+                            spawn_hud_value(parent, &game_assets, mode.wave_label(), "01".to_string(), HudWaveText);
+                            if *mode == GameMode::Endless {
+                                spawn_hud_value(parent, &game_assets, "TIME", format_duration(0), HudSurvivalText);
+                            }
+                        });
+                        parent.spawn(column(AlignItems::FlexEnd)).with_children(|parent| {
+                            spawn_player_hud_values(parent, &game_assets, players, "LIVES", |_| STARTING_LIVES.to_string(), HudLivesText);
+                            spawn_player_hud_values(parent, &game_assets, players, "BOMBS", |_| STARTING_BOMBS.to_string(), HudBombsText);
+                        });
+                    });
+
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            justify_content: JustifyContent::SpaceBetween,
+                            align_items: AlignItems::FlexEnd,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(controls());
+                        parent.spawn(power_ups(JustifyText::Right));
+                    });
+            });
+    }
+
+    fn score_system(
+        mut score_events: EventReader<ScoreEvent>,
+        mut score: ResMut<Score>,
+        mut pilots: ResMut<Pilots>,
+        mut chain: ResMut<Chain>,
+        challenge: Res<Challenge>,
+        mut scored_events: EventWriter<PointsScored>
+    ) {
+        for event in score_events.read() {
+            let points = event.points * challenge.score_percent / 100;
+            let (multiplier, label) = match event.reason {
+                ScoreReason::EnemyKilled => (chain.register_kill(), None),
+                ScoreReason::NoDamageWave => (1, Some("NO DAMAGE")),
+                ScoreReason::Accuracy => (1, Some("ACCURACY")),
+                ScoreReason::FormationCleared => (1, Some("FORMATION")),
+                ScoreReason::MiniBoss => (1, Some("MINI-BOSS")),
+            };
+            score.points += points * multiplier as u64;
+            if let Some(id) = event.player {
+                pilots.get_mut(id).score += points * multiplier as u64;
+            }
+
+            let text = match (label, multiplier) {
+                (Some(label), _) => format!("{label} +{points}"),
+                (None, 1) => format!("+{points}"),
+                (None, multiplier) => format!("+{points} x{multiplier}"),
+            };
+            scored_events.send(PointsScored { text, position: event.position });
+        }
+    }
+
+    fn score_popup_spawn_system(
+        mut commands: Commands,
+        mut scored_events: EventReader<PointsScored>,
+        game_assets: Res<GameAssets>
+    ) {
+        for event in scored_events.read() {
+            commands.spawn((
+                TextBundle {
+                    text: Text::from_section(event.text.clone(), game_assets.hud_text_style(WHITE.into())),
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    // placed by score_popup_system once its size is known
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                ScorePopup {
+                    timer: Timer::from_seconds(SCORE_POPUP_SECONDS, TimerMode::Once),
+                    position: event.position
+                },
+            ));
+        }
+    }
+
+    fn score_popup_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        screen_space: ScreenSpace,
+        mut query: Query<(Entity, &mut ScorePopup, &mut Style, &Node, &mut Visibility, &mut Text)>
+    ) {
+        for (entity, mut popup, mut style, node, mut visibility, mut text) in &mut query {
+            if popup.timer.tick(time.delta()).finished() {
+                commands.entity(entity).despawn();
+                continue;
+            }
+
+            popup.position.y += SCORE_POPUP_RISE_SPEED * time.delta_seconds();
+            let Some(point) = screen_space.world_to_hud(popup.position) else {
+                continue;
+            };
+            // centred on the point, the node's size is in the same unscaled units as its style
+            let half_size = node.size() / 2.0;
+            style.left = Val::Px(point.x - half_size.x);
+            style.top = Val::Px(point.y - half_size.y);
+            if node.size() != Vec2::ZERO {
+                *visibility = Visibility::Inherited;
+            }
+
+            let alpha = 1.0 - popup.timer.fraction();
+            for section in text.sections.iter_mut() {
+                section.style.color.set_alpha(alpha);
+            }
+        }
+    }
+
+    fn hud_score_system(
+        score: Res<Score>,
+        mut query: Query<&mut Text, With<ScoreCounterText>>
+    ) {
+        if !score.is_changed() {
+            return;
+        }
+        for mut text in &mut query {
+            text.sections[1].value = format_score(score.points);
+        }
+    }
+
+    fn hud_player_score_system(
+        pilots: Res<Pilots>,
+        mut query: Query<(&mut Text, &HudPlayerScoreText)>
+    ) {
+        if !pilots.is_changed() {
+            return;
+        }
+        for (mut text, HudPlayerScoreText(id)) in &mut query {
+            text.sections[1].value = format_score(pilots.get(*id).score);
+        }
+    }
+
+    fn hud_lives_system(
+        pilots: Res<Pilots>,
+        mut query: Query<(&mut Text, &HudLivesText)>
+    ) {
+        if !pilots.is_changed() {
+            return;
+        }
+        for (mut text, HudLivesText(id)) in &mut query {
+            text.sections[1].value = pilots.get(*id).lives.to_string();
+        }
+    }
+
+    fn hud_bombs_system(
+        pilots: Res<Pilots>,
+        mut query: Query<(&mut Text, &HudBombsText)>
+    ) {
+        if !pilots.is_changed() {
+            return;
+        }
+        for (mut text, HudBombsText(id)) in &mut query {
+            text.sections[1].value = pilots.get(*id).bombs.to_string();
+        }
+    }
+
+    fn hud_multiplier_system(
+        chain: Res<Chain>,
+        mut query: Query<&mut Text, With<HudMultiplierText>>
+    ) {
+        // the decay timer ticks every frame, so compare before touching the text
+        let value = format!("x{}", chain.multiplier);
+        for mut text in &mut query {
+            if text.sections[1].value != value {
+                text.sections[1].value = value.clone();
+            }
+        }
+    }
+
+    fn hud_wave_system(
+        wave: Res<Wave>,
+        mut query: Query<&mut Text, With<HudWaveText>>
+    ) {
+        if !wave.is_changed() {
+            return;
+        }
+        for mut text in &mut query {
+            text.sections[1].value = format!("{:02}", wave.number);
+        }
+    }
+
+    fn hud_survival_system(
+        survival: Res<Survival>,
+        mut query: Query<&mut Text, With<HudSurvivalText>>
+    ) {
+        for mut text in &mut query {
+            let value = format_duration(survival.seconds as u64);
+            if text.sections[1].value != value {
+                text.sections[1].value = value;
+            }
+        }
+    }
+
+    fn hud_weapon_system(
+        weapon_query: Query<(&Weapon, &PlayerId)>,
+        mut query: Query<(&mut Text, &HudWeaponText)>
+    ) {
+        for (weapon, id) in &weapon_query {
+            // charge changes every tick while fire is held, so compare before touching the text
+            let mut value = format!("{} LV{}", weapon.kind.label(), weapon.level);
+            if weapon.charge > 0.0 {
+                value.push_str(&format!(" {:>3}%", (weapon.charge * 100.0) as u32));
+            }
+            for (mut text, HudWeaponText(owner)) in &mut query {
+                if owner == id && text.sections[1].value != value {
+                    text.sections[1].value = value.clone();
+                }
+            }
+        }
+    }
+
+    fn hud_power_ups_system(
+        power_ups: Res<ActivePowerUps>,
+        mut query: Query<&mut Text, With<HudPowerUpsText>>
+    ) {
+        if !power_ups.is_changed() {
+            return;
+        }
+        let lines: Vec<String> = power_ups.0
+            .iter()
+            .map(|power_up| format!("{} {:>4.1}", power_up.name, power_up.timer.remaining_secs()))
+            .collect();
+        for mut text in &mut query {
+            text.sections[0].value = lines.join("\n");
+        }
+    }
+
+    #[derive(Component)]
+    struct OnSplashScreen;
+
+    #[derive(Resource, Deref, DerefMut)]
+    struct SplashTimer(Timer);
+
+    fn splash_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+        let icon = asset_server.load("branding/icon.png");
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                OnSplashScreen,
+            ))
+            .with_children(|parent| {
+                parent.spawn(ImageBundle {
+                    style: Style {
+                        width: Val::Px(200.0),
+                        ..default()
+                    },
+                    image: UiImage::new(icon),
+                    ..default()
+                });
+            })
+            .with_children(|parent| {
+                // shown before anything is loaded, in Bevy's own face
+                parent.spawn(
+                    TextBundle::from_section(
+                        "made with bevy",
+                        TextStyle {
+                            font_size: 80.0,
+                            color: WHITE.into(),
+                            ..default()
+                        },
+                    ),
+                );
+            });
+        commands.insert_resource(SplashTimer(Timer::from_seconds(1.0, TimerMode::Once)));
+    }
+
+
+    fn countdown(
+        mut commands: Commands,
+        mut game_state: ResMut<NextState<GameState>>,
+        time: Res<Time>,
+        timer: Option<ResMut<SplashTimer>>,
+        mut query: Query<Entity, With<UiImage>>,
+        mut text_query: Query<Entity, With<Text>>
+    ) {
+        if timer.is_none() {
+            return;
+        }
+
+            if let Some(mut timer) = timer {
+                if timer.tick(time.delta()).finished() {
+                    for image in &mut query {
+                        game_state.set(GameState::Loading);
+                        commands.entity(image).despawn();
+                        commands.remove_resource::<SplashTimer>();
+                    }
+                    for text in &mut text_query {
+                        commands.entity(text).despawn();
+                    }
+                }
+            } else {
+                eprintln!("SplashTimer resource not found!");
+            }
+    }
+
+    #[derive(Component)]
+    struct OnLoadingScreen;
+
+    #[derive(Component)]
+    struct LoadingProgressBar;
+
+    // the heading, which says what went wrong instead if something can't be loaded
+    #[derive(Component)]
+    struct LoadingStatusText;
+
+    /// Assets gameplay spawns with, loaded once in `GameState::Loading` so systems only clone
+    /// handles instead of hitting the asset server per shot or hit.
+    #[derive(Resource)]
+    struct GameAssets {
+        sprites: Handle<SpriteManifest>,
+        weapons: Handle<WeaponManifest>,
+        waves: Handle<WaveManifest>,
+        font: Handle<Font>,
+    }
+
+    fn loading_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+        commands.insert_resource(GameAssets {
+            sprites: asset_server.load("manifest.sprites.ron"),
+            weapons: asset_server.load("player.weapons.ron"),
+            waves: asset_server.load("campaign.waves.ron"),
+            font: asset_server.load("fonts/pixel-5x7.ttf"),
+        });
+
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                OnLoadingScreen,
+            ))
+            .with_children(|parent| {
+                // Bevy's built-in face, so it can still say when the pixel font is what didn't load
+                parent.spawn((
+                    TextBundle::from_section(
+                        "Loading",
+                        TextStyle {
+                            font_size: 40.0,
+                            color: WHITE.into(),
+                            ..default()
+                        },
+                    )
+                        .with_text_justify(JustifyText::Center)
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(20.0)),
+                            ..default()
+                        }),
+                    LoadingStatusText,
+                ));
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(400.0),
+                            height: Val::Px(24.0),
+                            border: UiRect::all(Val::Px(2.0)),
+                            ..default()
+                        },
+                        border_color: WHITE.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Percent(0.0),
+                                    height: Val::Percent(100.0),
+                                    ..default()
+                                },
+                                background_color: PRESSED_BUTTON.into(),
+                                ..default()
+                            },
+                            LoadingProgressBar,
+                        ));
+                    });
+            });
+    }
+
+    fn loading_progress_system(
+        asset_server: Res<AssetServer>,
+        game_assets: Res<GameAssets>,
+        manifests: Res<Assets<SpriteManifest>>,
+        mut game_state: ResMut<NextState<GameState>>,
+        mut bar_query: Query<&mut Style, With<LoadingProgressBar>>,
+        mut status_query: Query<&mut Text, With<LoadingStatusText>>,
+    ) {
+        let mut failure = None;
+        // each manifest and the font count as one step, each texture the sprite manifest references as another
+        let (mut loaded, mut steps) = match asset_server.get_recursive_dependency_load_state(&game_assets.sprites) {
+            Some(RecursiveDependencyLoadState::Loaded) => (1, 1),
+            Some(RecursiveDependencyLoadState::Failed) => {
+                failure = Some(match asset_server.get_load_state(&game_assets.sprites) {
+                    Some(LoadState::Failed(error)) => format!("Could not load the sprite manifest\n{error}"),
+                    _ => "Could not load one of the sprite textures".to_string(),
+                });
+                (0, 1)
+            }
+            _ => match manifests.get(&game_assets.sprites) {
+                Some(manifest) => {
+                    let textures = manifest.sheets.values().map(|sheet| &sheet.texture);
+                    let loaded = textures.clone().filter(|texture| asset_server.is_loaded_with_dependencies(*texture)).count();
+                    (1 + loaded, 1 + textures.count())
+                }
+                None => (0, 1),
+            },
+        };
+        let others = [
+            ("weapon manifest", game_assets.weapons.id().untyped()),
+            ("wave manifest", game_assets.waves.id().untyped()),
+            ("HUD font", game_assets.font.id().untyped()),
+        ];
+        for (name, id) in others {
+            match asset_server.get_load_state(id) {
+                Some(LoadState::Loaded) => loaded += 1,
+                Some(LoadState::Failed(error)) => failure = Some(format!("Could not load the {name}\n{error}")),
+                _ => {}
+            }
+            steps += 1;
+        }
+        let progress = loaded as f32 / steps as f32;
+
+        // stays up until the files are fixed, they're reloaded as they're saved
+        if let Some(failure) = failure {
+            for mut text in &mut status_query {
+                if text.sections[0].value != failure {
+                    error!("{}", failure.replace('\n', ": "));
+                    text.sections[0].value = failure.clone();
+                }
+            }
+            return;
+        }
+
+        for mut style in &mut bar_query {
+            style.width = Val::Percent(100.0 * progress);
+        }
+
+        if progress >= 1.0 {
+            // a run left unfinished last time is offered on the menu rather than started over
+            if Checkpoint::path().exists() {
+                game_state.set(GameState::Over);
+            } else {
+                game_state.set(GameState::Game);
+            }
+        }
+    }
+
+    #[derive(Component)]
+    struct OnGameScreen;
+
+    /// Buttons hovered, pressed or let go of since the last frame.
+    type ChangedButton = (Changed<Interaction>, With<Button>);
+
+    fn button_system(
+        mut interaction_query: Query<(&Interaction, &mut BackgroundColor, Option<&SelectedOption>), ChangedButton>,
+    ) {
+        for (interaction, mut background_color, selected) in &mut interaction_query {
+            *background_color = match (*interaction, selected) {
+                (Interaction::Pressed, _) | (Interaction::None, Some(_)) => PRESSED_BUTTON.into(),
+                (Interaction::Hovered, Some(_)) => HOVERED_PRESSED_BUTTON.into(),
+                (Interaction::Hovered, None) => HOVERED_BUTTON.into(),
+                (Interaction::None, None) => NORMAL_BUTTON.into(),
+            }
+        }
+    }
+
+    fn menu_setup(mut menu_state: ResMut<NextState<MenuState>>) {
+        menu_state.set(MenuState::Main);
+    }
+
+    fn spawn_menu_button(parent: &mut ChildBuilder, game_assets: &GameAssets, label: &str, action: MenuButtonAction) {
+        let button_style = Style {
+            width: Val::Px(250.0),
+            height: Val::Px(65.0),
+            margin: UiRect::all(Val::Px(20.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        };
+        let button_icon_style = Style {
+            width: Val::Px(30.0),
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.0),
+            ..default()
+        };
+        let button_text_style = game_assets.text_style(40.0, WHITE.into());
 
-                    let animation_config_1 = AnimationConfig::new(0, 4, 10);
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: button_style,
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                },
+                action,
+            ))
+            .with_children(|parent| {
+                parent.spawn(ImageBundle {
+                    style: button_icon_style,
+                    ..default()
+                });
+                parent.spawn(TextBundle::from_section(
+                    label,
+                    button_text_style,
+                ));
+            });
+    }
 
-                    commands.spawn((
-                        SpriteBundle {
-                            transform: Transform::from_scale(Vec3::splat(6.0))
-                                .with_translation(Vec3::new(player.translation.x, player.translation.y, 0.0)),
-                            texture,
+    fn main_menu_setup(mut commands: Commands, score: Option<Res<Score>>, game_assets: Res<GameAssets>) {
+        // no run has been played yet when the game starts here to offer the unfinished one
+        let title = if score.is_some() { "Game Over" } else { "Welcome Back" };
+
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                },
+                OnMainMenuScreen,
+            ))
+            .with_children(|parent| {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
                             ..default()
                         },
-                        TextureAtlas {
-                            layout: texture_atlas_layout,
-                            index: animation_config_1.first_sprite_index,
+                        background_color: CRIMSON.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        // Display the game name
+                        parent.spawn(
+                            TextBundle::from_section(
+                                title,
+                                game_assets.text_style(80.0, WHITE.into()),
+                            )
+                                .with_style(Style {
+                                    margin: UiRect::new(Val::Px(50.0), Val::Px(50.0), Val::Px(50.0), Val::Px(10.0)),
+                                    ..default()
+                                }),
+                        );
+                        if let Some(score) = &score {
+                            parent.spawn(TextBundle::from_section(
+                                format!("SCORE {}", format_score(score.points)),
+                                game_assets.hud_text_style(WHITE.into()),
+                            ));
+                        }
+
+                        if Checkpoint::path().exists() {
+                            spawn_menu_button(parent, &game_assets, "Continue", MenuButtonAction::Continue);
+                        }
+                        spawn_menu_button(parent, &game_assets, "New Game", MenuButtonAction::NewGame);
+                        spawn_menu_button(parent, &game_assets, "High Scores", MenuButtonAction::HighScores);
+                        spawn_menu_button(parent, &game_assets, "Settings", MenuButtonAction::Settings);
+                    });
+            });
+    }
+
+    fn menu_action(
+        interaction_query: Query<(&Interaction, &MenuButtonAction), ChangedButton>,
+        mut menu_state: ResMut<NextState<MenuState>>,
+        mut game_state: ResMut<NextState<GameState>>,
+        mut commands: Commands,
+        net_settings: Res<NetSettings>,
+        lobby: Option<Res<NetLobby>>,
+        mut status_query: Query<&mut Text, With<NetStatusText>>,
+    ) {
+        for (interaction, menu_button_action) in &interaction_query {
+            if *interaction == Interaction::Pressed {
+
+                match menu_button_action {
+                    MenuButtonAction::NewGame => menu_state.set(MenuState::NewGame),
+                    MenuButtonAction::Continue => commands.add(load_checkpoint),
+                    MenuButtonAction::Play => {
+                        // a networked run just finished may still be saying goodbye
+                        commands.remove_resource::<NetSession>();
+                        game_state.set(GameState::Game);
+                        menu_state.set(MenuState::Disabled);
+                    }
+                    MenuButtonAction::Online => menu_state.set(MenuState::Online),
+                    // already hosting or joining, going back cancels it
+                    MenuButtonAction::Host | MenuButtonAction::Join if lobby.is_some() => {}
+                    MenuButtonAction::Host | MenuButtonAction::Join => {
+                        let lobby = match menu_button_action {
+                            MenuButtonAction::Host => NetLobby::host(&net_settings),
+                            _ => NetLobby::join(&net_settings),
+                        };
+                        let status = match lobby {
+                            Ok(lobby) => {
+                                let status = lobby.status();
+                                commands.insert_resource(lobby);
+                                status
+                            }
+                            Err(error) => error,
+                        };
+                        for mut text in &mut status_query {
+                            text.sections[0].value = status.clone();
+                        }
+                    }
+                    MenuButtonAction::HighScores => menu_state.set(MenuState::HighScores),
+                    MenuButtonAction::Settings => menu_state.set(MenuState::Settings),
+                    MenuButtonAction::SettingsDisplay => {
+                        menu_state.set(MenuState::SettingsDisplay);
+                    }
+                    MenuButtonAction::BackToMainMenu => menu_state.set(MenuState::Main),
+                    MenuButtonAction::BackToNewGame => menu_state.set(MenuState::NewGame),
+                    MenuButtonAction::BackToSettings => {
+                        menu_state.set(MenuState::Settings);
+                    }
+                }
+            }
+        }
+    }
+
+    // the box every settings screen is laid out in
+    fn spawn_settings_screen(commands: &mut Commands, game_assets: &GameAssets, screen: impl Component, title: &str, spawn_children: impl FnOnce(&mut ChildBuilder)) {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                },
+                screen,
+            ))
+            .with_children(|parent| {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            padding: UiRect::all(Val::Px(30.0)),
+                            ..default()
                         },
-                        animation_config_1,
-                        Explosion {
-                            frame_timer: 0.0
+                        background_color: CRIMSON.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            title,
+                            game_assets.text_style(60.0, WHITE.into()),
+                        ));
+                        spawn_children(parent);
+                    });
+            });
+    }
+
+    fn new_game_menu_setup(
+        mut commands: Commands,
+        mode: Res<GameMode>,
+        difficulty: Res<Difficulty>,
+        coop: Res<CoopSettings>,
+        game_assets: Res<GameAssets>
+    ) {
+        spawn_settings_screen(&mut commands, &game_assets, OnNewGameScreen, "New Game", |parent| {
+            spawn_option_row::<GameMode>(parent, &game_assets, "MODE", &mode);
+            spawn_option_row::<Difficulty>(parent, &game_assets, "DIFFICULTY", &difficulty);
+            spawn_option_row::<PlayerCount>(parent, &game_assets, "PLAYERS", &coop);
+            spawn_option_row::<LifeSharing>(parent, &game_assets, "LIVES", &coop);
+            spawn_button_row(parent, |parent| {
+                spawn_menu_button(parent, &game_assets, "Start", MenuButtonAction::Play);
+                spawn_menu_button(parent, &game_assets, "Online", MenuButtonAction::Online);
+                spawn_menu_button(parent, &game_assets, "Back", MenuButtonAction::BackToMainMenu);
+            });
+        });
+    }
+
+    #[derive(Component)]
+    struct JoinAddressText;
+
+    #[derive(Component)]
+    struct NetStatusText;
+
+    /// Hosting and joining a two player game over the network. The host's mode, difficulty and
+    /// lives from the new game screen are the ones played.
+    fn online_menu_setup(mut commands: Commands, settings: Res<NetSettings>, game_assets: Res<GameAssets>) {
+        spawn_settings_screen(&mut commands, &game_assets, OnOnlineScreen, "Online", |parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        margin: UiRect::vertical(Val::Px(10.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section("ADDRESS", game_assets.hud_text_style(HUD_LABEL_COLOR))
+                            .with_style(Style {
+                                width: Val::Px(200.0),
+                                ..default()
+                            }),
+                    );
+                    parent.spawn((
+                        TextBundle::from_section(settings.address.clone(), game_assets.hud_text_style(WHITE.into()))
+                            .with_style(Style {
+                                width: Val::Px(680.0),
+                                ..default()
+                            }),
+                        JoinAddressText,
+                    ));
+                });
+            spawn_option_row::<SimulatedLag>(parent, &game_assets, "TEST LAG", &settings);
+            spawn_option_row::<SimulatedLoss>(parent, &game_assets, "TEST LOSS", &settings);
+            parent.spawn((
+                TextBundle::from_section("Type the host's address to join", game_assets.hud_text_style(HUD_LABEL_COLOR)),
+                NetStatusText,
+            ));
+            spawn_button_row(parent, |parent| {
+                spawn_menu_button(parent, &game_assets, "Host", MenuButtonAction::Host);
+                spawn_menu_button(parent, &game_assets, "Join", MenuButtonAction::Join);
+                spawn_menu_button(parent, &game_assets, "Back", MenuButtonAction::BackToNewGame);
+            });
+        });
+    }
+
+    /// Edits the address to join on the online screen as it's typed.
+    fn join_address_system(
+        mut keyboard_events: EventReader<KeyboardInput>,
+        mut settings: ResMut<NetSettings>,
+        mut save_data: ResMut<SaveData>,
+        mut text_query: Query<&mut Text, With<JoinAddressText>>
+    ) {
+        let mut changed = false;
+        for event in keyboard_events.read() {
+            if event.state != ButtonState::Pressed {
+                continue;
+            }
+            match &event.logical_key {
+                Key::Backspace => changed |= settings.address.pop().is_some(),
+                Key::Character(characters) => {
+                    // host names, IPv4 and bracketed IPv6 addresses, with a port
+                    for character in characters.chars().filter(|c| c.is_ascii_alphanumeric() || ".:-[]".contains(*c)) {
+                        if settings.address.len() < NET_ADDRESS_LENGTH {
+                            settings.address.push(character);
+                            changed = true;
                         }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !changed {
+            return;
+        }
+
+        for mut text in &mut text_query {
+            text.sections[0].value = settings.address.clone();
+        }
+        settings.store(&mut save_data);
+        save_data.write();
+    }
+
+    // buttons side by side, for screens too tall to stack them
+    fn spawn_button_row(parent: &mut ChildBuilder, spawn_buttons: impl FnOnce(&mut ChildBuilder)) {
+        parent.spawn(NodeBundle::default()).with_children(spawn_buttons);
+    }
+
+    fn settings_menu_setup(mut commands: Commands, game_assets: Res<GameAssets>) {
+        spawn_settings_screen(&mut commands, &game_assets, OnSettingsMenuScreen, "Settings", |parent| {
+            spawn_menu_button(parent, &game_assets, "Display", MenuButtonAction::SettingsDisplay);
+            spawn_menu_button(parent, &game_assets, "Back", MenuButtonAction::BackToMainMenu);
+        });
+    }
+
+    fn display_settings_menu_setup(mut commands: Commands, settings: Res<DisplaySettings>, game_assets: Res<GameAssets>) {
+        spawn_settings_screen(&mut commands, &game_assets, OnDisplaySettingsMenuScreen, "Display", |parent| {
+            spawn_option_row::<Layout>(parent, &game_assets, "LAYOUT", &settings);
+            spawn_option_row::<ParticleDensity>(parent, &game_assets, "PARTICLES", &settings);
+            spawn_option_row::<EffectIntensity>(parent, &game_assets, "EFFECTS", &settings);
+            spawn_menu_button(parent, &game_assets, "Back", MenuButtonAction::BackToSettings);
+        });
+    }
+
+    /// A label followed by one button per value of `T`, with the current value selected.
+    fn spawn_option_row<T: MenuOption>(parent: &mut ChildBuilder, game_assets: &GameAssets, label: &str, settings: &T::Settings) {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    margin: UiRect::vertical(Val::Px(10.0)),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn(
+                    TextBundle::from_section(label, game_assets.hud_text_style(HUD_LABEL_COLOR))
+                        .with_style(Style {
+                            width: Val::Px(200.0),
+                            ..default()
+                        }),
+                );
+                for &option in T::ALL {
+                    let mut button = parent.spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(160.0),
+                                height: Val::Px(50.0),
+                                margin: UiRect::all(Val::Px(10.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: NORMAL_BUTTON.into(),
+                            ..default()
+                        },
+                        option,
                     ));
+                    if option == T::get(settings) {
+                        button.insert((SelectedOption, BackgroundColor(PRESSED_BUTTON)));
+                    }
+                    button.with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(option.label(), game_assets.hud_text_style(WHITE.into())));
+                    });
+                }
+            });
+    }
+
+    /// The option button of a setting that is currently chosen.
+    type Selected<T> = (With<SelectedOption>, With<T>);
+
+    /// Applies a pressed option button, moves the selection to it and saves the change.
+    fn option_button_system<T: MenuOption>(
+        mut commands: Commands,
+        interaction_query: Query<(&Interaction, &T, Entity), ChangedButton>,
+        mut selected_query: Query<(Entity, &mut BackgroundColor), Selected<T>>,
+        mut settings: ResMut<T::Settings>,
+        mut save_data: ResMut<SaveData>,
+    ) {
+        for (interaction, option, entity) in &interaction_query {
+            if *interaction != Interaction::Pressed || *option == T::get(&settings) {
+                continue;
+            }
+            for (previous, mut background_color) in &mut selected_query {
+                *background_color = NORMAL_BUTTON.into();
+                commands.entity(previous).remove::<SelectedOption>();
+            }
+            commands.entity(entity).insert(SelectedOption);
+            option.set(&mut settings);
+            settings.store(&mut save_data);
+            save_data.write();
+        }
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct HighScoreEntry {
+        name: String,
+        score: u64,
+        mode: GameMode,
+        difficulty: Difficulty,
+        seed: u64,
+        // unix seconds the run started at
+        date: u64,
+        // seconds the run lasted, listed on the endless leaderboard
+        #[serde(default)]
+        survived: u64
+    }
+
+    // writes one of the files kept in the user's data directory, creating the directory if need be
+    fn write_data_file(path: &Path, contents: &str) {
+        if let Some(parent) = path.parent() {
+            if let Err(error) = fs::create_dir_all(parent) {
+                error!("could not create save directory {}: {}", parent.display(), error);
+                return;
+            }
+        }
+        if let Err(error) = fs::write(path, contents) {
+            error!("could not write {}: {}", path.display(), error);
+        }
+    }
+
+    /// Everything persisted between sessions, stored as RON in the user's data directory.
+    #[derive(Resource, Default, Serialize, Deserialize)]
+    struct SaveData {
+        #[serde(default)]
+        high_scores: Vec<HighScoreEntry>,
+        #[serde(default)]
+        display: DisplaySettings,
+        #[serde(default)]
+        difficulty: Difficulty,
+        #[serde(default)]
+        mode: GameMode,
+        #[serde(default)]
+        coop: CoopSettings,
+        #[serde(default)]
+        net: NetSettings,
+        #[serde(default)]
+        simulation: SimulationSettings
+    }
 
-                    game_state.set(GameState::Over);
+    impl SaveData {
+        fn path() -> PathBuf {
+            match directories::ProjectDirs::from("", "", env!("CARGO_PKG_NAME")) {
+                Some(dirs) => dirs.data_dir().join(SAVE_FILE_NAME),
+                None => PathBuf::from(SAVE_FILE_NAME),
+            }
+        }
+
+        fn load() -> Self {
+            let path = Self::path();
+            let Ok(contents) = fs::read_to_string(&path) else {
+                return Self::default();
+            };
+            match ron::from_str(&contents) {
+                Ok(save_data) => save_data,
+                Err(error) => {
+                    warn!("ignoring unreadable save file {}: {}", path.display(), error);
+                    Self::default()
                 }
             }
         }
+
+        fn write(&self) {
+            match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+                Ok(contents) => write_data_file(&Self::path(), &contents),
+                Err(error) => error!("could not serialize save data: {}", error),
+            }
+        }
+
+        /// The table for one mode and difficulty, best first.
+        fn high_score_table(&self, mode: GameMode, difficulty: Difficulty) -> Vec<&HighScoreEntry> {
+            let mut table: Vec<&HighScoreEntry> = self.high_scores
+                .iter()
+                .filter(|entry| entry.mode == mode && entry.difficulty == difficulty)
+                .collect();
+            table.sort_by_key(|entry| Reverse(entry.score));
+            table.truncate(HIGH_SCORE_TABLE_SIZE);
+            table
+        }
+
+        fn qualifies(&self, mode: GameMode, difficulty: Difficulty, score: u64) -> bool {
+            let table = self.high_score_table(mode, difficulty);
+            score > 0 && (table.len() < HIGH_SCORE_TABLE_SIZE || table.last().is_some_and(|lowest| score > lowest.score))
+        }
+
+        fn add_high_score(&mut self, entry: HighScoreEntry) {
+            let (mode, difficulty) = (entry.mode, entry.difficulty);
+            self.high_scores.push(entry);
+
+            // keep every table at its top entries, ties go to whoever got there first
+            let mut table_indices: Vec<usize> = (0..self.high_scores.len())
+                .filter(|&i| self.high_scores[i].mode == mode && self.high_scores[i].difficulty == difficulty)
+                .collect();
+            table_indices.sort_by(|&a, &b| self.high_scores[b].score.cmp(&self.high_scores[a].score).then(a.cmp(&b)));
+            let dropped: Vec<usize> = table_indices.into_iter().skip(HIGH_SCORE_TABLE_SIZE).collect();
+            let mut index = 0;
+            self.high_scores.retain(|_| {
+                let keep = !dropped.contains(&index);
+                index += 1;
+                keep
+            });
+        }
+    }
+
+    fn load_save_data(mut commands: Commands) {
+        let save_data = SaveData::load();
+        commands.insert_resource(save_data.display.clone());
+        commands.insert_resource(save_data.difficulty);
+        commands.insert_resource(save_data.mode);
+        commands.insert_resource(save_data.coop.clone());
+        commands.insert_resource(save_data.net.clone());
+        commands.insert_resource(save_data.simulation.clamped());
+        commands.insert_resource(save_data);
+    }
+
+    /// What a finished run needs to decide on and record a high score.
+    #[derive(SystemParam)]
+    struct RunResult<'w> {
+        score: Res<'w, Score>,
+        run: Res<'w, RunInfo>,
+        mode: Res<'w, GameMode>,
+        difficulty: Res<'w, Difficulty>,
+        survival: Res<'w, Survival>,
+    }
+
+    impl RunResult<'_> {
+        fn qualifies_for_high_score(&self, save_data: &SaveData) -> bool {
+            save_data.qualifies(*self.mode, *self.difficulty, self.score.points)
+        }
+
+        fn high_score_entry(&self, name: String) -> HighScoreEntry {
+            HighScoreEntry {
+                name,
+                score: self.score.points,
+                mode: *self.mode,
+                difficulty: *self.difficulty,
+                seed: self.run.seed,
+                date: self.run.started_at,
+                survived: self.survival.seconds as u64,
+            }
+        }
+    }
+
+    /// Ends a run for good: on to entering a name if it made the high scores, otherwise to the
+    /// game over screen.
+    #[derive(SystemParam)]
+    struct RunEnd<'w> {
+        run_result: RunResult<'w>,
+        save_data: Res<'w, SaveData>,
+        game_state: ResMut<'w, NextState<GameState>>,
+    }
+
+    impl RunEnd<'_> {
+        fn finish(&mut self) {
+            if self.run_result.qualifies_for_high_score(&self.save_data) {
+                self.game_state.set(GameState::NameEntry);
+            } else {
+                self.game_state.set(GameState::Over);
+            }
+        }
+    }
+
+    fn format_duration(seconds: u64) -> String {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+
+    fn format_date(unix_seconds: u64) -> String {
+        // days since the epoch to a proleptic Gregorian date, see
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let days = (unix_seconds / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+
+    /// Arcade style continue, counting down once a campaign run is lost. Taking it plays the
+    /// checkpoint's wave again with a fresh set of lives and bombs, but the score back at zero.
+    #[derive(Resource, Deref, DerefMut)]
+    struct ContinueCountdown(Timer);
+
+    #[derive(Component)]
+    struct OnContinueScreen;
+
+    #[derive(Component)]
+    struct ContinueCountdownText;
+
+    fn continue_setup(mut commands: Commands, checkpoint: Res<Checkpoint>, game_assets: Res<GameAssets>) {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                },
+                OnContinueScreen,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "CONTINUE?",
+                    game_assets.text_style(60.0, WHITE.into()),
+                ));
+                parent.spawn((
+                    TextBundle::from_section(
+                        format!("{}", CONTINUE_SECONDS as u32),
+                        game_assets.text_style(80.0, WHITE.into()),
+                    ),
+                    ContinueCountdownText,
+                ));
+                parent.spawn(TextBundle::from_section(
+                    format!("{} {} AGAIN, SCORE FROM ZERO", GameMode::Campaign.wave_label(), checkpoint.wave),
+                    game_assets.hud_text_style(HUD_LABEL_COLOR),
+                ));
+                parent.spawn(TextBundle::from_section(
+                    "Enter/A: Continue  Esc/B: Give Up",
+                    game_assets.hud_text_style(HUD_LABEL_COLOR),
+                ));
+            });
     }
 
-    fn enemy_spawn_system(
-        mut commands: Commands,
-        asset_server: Res<AssetServer>,
-        mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-        query: Query<&SpawnTimer>
-    ) {
-        if query.is_empty() {
-            return;
-        }
-
-        let spawn_timer = query.single();
-        if spawn_timer.timer <= 1.0 {
-            let enemy_a_handle = asset_server.load("Spaceship-shooter-gamekit/Assets/spritesheets/enemy-medium.png");
-
-            let layout = TextureAtlasLayout::from_grid(UVec2::new(32, 16), 2, 1, None, None);
-            let texture_atlas_layout_medium = texture_atlas_layouts.add(layout);
+    /// Confirming and backing out of a prompt, from the keyboard or either player's gamepad.
+    #[derive(SystemParam)]
+    struct MenuInput<'w> {
+        keyboard: Res<'w, ButtonInput<KeyCode>>,
+        buttons: Res<'w, ButtonInput<GamepadButton>>,
+    }
 
-            let animation_config_2 = AnimationConfig::new(0, 1, 10);
+    impl MenuInput<'_> {
+        fn just_pressed(&self, key: KeyCode, button_type: GamepadButtonType) -> bool {
+            self.keyboard.just_pressed(key) || self.buttons.get_just_pressed().any(|button| button.button_type == button_type)
+        }
 
-            let randgennumb: f32 = rand::thread_rng().gen_range(-1200..1200).to_string().parse().unwrap();
+        fn confirmed(&self) -> bool {
+            self.just_pressed(KeyCode::Enter, GamepadButtonType::South)
+        }
 
-            commands.spawn((
-                SpriteBundle {
-                    transform: Transform::from_scale(Vec3::splat(3.0))
-                        .with_translation(Vec3::new(randgennumb, 640.0, 0.0)),
-                    texture: enemy_a_handle.clone(),
-                    ..default()
-                },
-                TextureAtlas {
-                    layout: texture_atlas_layout_medium.clone(),
-                    index: animation_config_2.first_sprite_index,
-                },
-                Enemy {
-                    is_hit: false,
-                    position: Vec3::new(randgennumb, 300.0, 0.0),
-                    movement_speed: 250.0,
-                }
-            ));
+        fn cancelled(&self) -> bool {
+            self.just_pressed(KeyCode::Escape, GamepadButtonType::East)
         }
     }
 
-    fn spawn_timer_system(
-        mut query: Query<&mut SpawnTimer>
+    fn continue_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        input: MenuInput,
+        mut countdown: ResMut<ContinueCountdown>,
+        mut run_end: RunEnd,
+        mut text_query: Query<&mut Text, With<ContinueCountdownText>>,
+        screen_query: Query<Entity, With<OnContinueScreen>>
     ) {
-        if query.is_empty() {
-            return;
+        let taken = input.confirmed();
+        let given_up = input.cancelled() || countdown.tick(time.delta()).finished();
+        if taken || given_up {
+            commands.remove_resource::<ContinueCountdown>();
+            for entity in &screen_query {
+                commands.entity(entity).despawn_recursive();
+            }
         }
 
-        let mut spawn_timer = query.single_mut();
-        spawn_timer.timer -= 1.0;
-        if spawn_timer.timer < 1.0 {
-            spawn_timer.timer = 30.0;
+        if taken {
+            commands.add(continue_run);
+        } else if given_up {
+            Checkpoint::delete();
+            run_end.finish();
+        } else {
+            for mut text in &mut text_query {
+                text.sections[0].value = format!("{}", countdown.remaining_secs().ceil() as u32);
+            }
         }
     }
 
+    // the continue taken: back to the checkpoint, as if on a new credit
+    fn continue_run(world: &mut World) {
+        resume_checkpoint(world);
+        let players = world.resource::<CoopSettings>().players;
+        world.insert_resource(Pilots::new(players));
+        world.insert_resource(Score::default());
+        world.insert_resource(BombRequested::default());
+    }
+
     #[derive(Component)]
-    struct OnSplashScreen;
+    struct OnNameEntryScreen;
 
-    #[derive(Resource, Deref, DerefMut)]
-    struct SplashTimer(Timer);
+    #[derive(Component)]
+    struct NameEntryLetter(usize);
+
+    /// Arcade style initials: up and down pick a letter, left and right move between them.
+    #[derive(Resource)]
+    struct NameEntry {
+        letters: [u8; HIGH_SCORE_NAME_LENGTH],
+        cursor: usize
+    }
+
+    fn name_entry_setup(mut commands: Commands, score: Res<Score>, game_assets: Res<GameAssets>) {
+        commands.insert_resource(NameEntry {
+            letters: [b'A'; HIGH_SCORE_NAME_LENGTH],
+            cursor: 0
+        });
+
+        let letter_style = game_assets.text_style(80.0, WHITE.into());
 
-    fn splash_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-        let icon = asset_server.load("branding/icon.png");
         commands
             .spawn((
                 NodeBundle {
                     style: Style {
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::Center,
                         width: Val::Percent(100.0),
                         height: Val::Percent(100.0),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
                         ..default()
                     },
                     ..default()
                 },
-                OnSplashScreen,
+                OnNameEntryScreen,
             ))
             .with_children(|parent| {
-                parent.spawn(ImageBundle {
-                    style: Style {
-                        width: Val::Px(200.0),
-                        ..default()
-                    },
-                    image: UiImage::new(icon),
-                    ..default()
-                });
-            })
-            .with_children(|parent| {
-                parent.spawn(
-                    TextBundle::from_section(
-                        "made with bevy",
-                        TextStyle {
-                            font_size: 80.0,
-                            color: WHITE.into(),
+                parent.spawn(TextBundle::from_section(
+                    "NEW HIGH SCORE",
+                    game_assets.text_style(60.0, WHITE.into()),
+                ));
+                parent.spawn(TextBundle::from_section(
+                    format_score(score.points),
+                    game_assets.hud_text_style(WHITE.into()),
+                ));
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            margin: UiRect::all(Val::Px(30.0)),
+                            column_gap: Val::Px(20.0),
                             ..default()
                         },
-                    ),
-                );
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for index in 0..HIGH_SCORE_NAME_LENGTH {
+                            parent.spawn((
+                                TextBundle::from_section("A", letter_style.clone()),
+                                NameEntryLetter(index),
+                            ));
+                        }
+                    });
+                parent.spawn(TextBundle::from_section(
+                    "Up/Down: Letter  Left/Right: Move  Enter: Confirm",
+                    game_assets.hud_text_style(HUD_LABEL_COLOR),
+                ));
             });
-        commands.insert_resource(SplashTimer(Timer::from_seconds(1.0, TimerMode::Once)));
     }
 
-
-    fn countdown(
-        mut commands: Commands,
+    fn name_entry_system(
+        keyboard_input: Res<ButtonInput<KeyCode>>,
+        mut entry: ResMut<NameEntry>,
+        mut letter_query: Query<(&NameEntryLetter, &mut Text)>,
+        mut save_data: ResMut<SaveData>,
+        run_result: RunResult,
         mut game_state: ResMut<NextState<GameState>>,
-        time: Res<Time>,
-        mut timer: Option<ResMut<SplashTimer>>,
-        mut query: Query<Entity, With<UiImage>>,
-        mut text_query: Query<Entity, With<Text>>
     ) {
-        if timer.is_none() {
-            return;
+        let cursor = entry.cursor;
+        if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+            entry.letters[cursor] = if entry.letters[cursor] == b'Z' { b'A' } else { entry.letters[cursor] + 1 };
+        }
+        if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+            entry.letters[cursor] = if entry.letters[cursor] == b'A' { b'Z' } else { entry.letters[cursor] - 1 };
+        }
+        if keyboard_input.just_pressed(KeyCode::ArrowLeft) && cursor > 0 {
+            entry.cursor -= 1;
+        }
+        if keyboard_input.just_pressed(KeyCode::ArrowRight) && cursor + 1 < HIGH_SCORE_NAME_LENGTH {
+            entry.cursor += 1;
         }
 
-            if let Some(mut timer) = timer {
-                if timer.tick(time.delta()).finished() {
-                    for image in &mut query {
-                        game_state.set(GameState::Game);
-                        commands.entity(image).despawn();
-                        commands.remove_resource::<SplashTimer>();
-                    }
-                    for text in &mut text_query {
-                        commands.entity(text).despawn();
-                    }
-                }
-            } else {
-                eprintln!("SplashTimer resource not found!");
-            }
-    }
-
-    #[derive(Component)]
-    struct OnGameScreen;
+        if keyboard_input.just_pressed(KeyCode::Enter) {
+            let name = String::from_utf8_lossy(&entry.letters).into_owned();
+            save_data.add_high_score(run_result.high_score_entry(name));
+            save_data.write();
+            game_state.set(GameState::Over);
+            return;
+        }
 
-    fn button_system(
-        mut interaction_query: Query<
-            (&Interaction, &mut BackgroundColor, Option<&SelectedOption>),
-            (Changed<Interaction>, With<Button>),
-        >,
-    ) {
-        for (interaction, mut background_color, selected) in &mut interaction_query {
-            *background_color = match (*interaction, selected) {
-                (Interaction::Pressed, _) | (Interaction::None, Some(_)) => PRESSED_BUTTON.into(),
-                (Interaction::Hovered, Some(_)) => HOVERED_PRESSED_BUTTON.into(),
-                (Interaction::Hovered, None) => HOVERED_BUTTON.into(),
-                (Interaction::None, None) => NORMAL_BUTTON.into(),
+        if entry.is_changed() {
+            for (letter, mut text) in &mut letter_query {
+                text.sections[0].value = (entry.letters[letter.0] as char).to_string();
+                text.sections[0].style.color = if letter.0 == entry.cursor { PRESSED_BUTTON } else { WHITE.into() };
             }
         }
     }
 
-    fn menu_setup(mut menu_state: ResMut<NextState<MenuState>>) {
-        menu_state.set(MenuState::Main);
-    }
-
-    fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-        let button_style = Style {
-            width: Val::Px(250.0),
-            height: Val::Px(65.0),
-            margin: UiRect::all(Val::Px(20.0)),
-            justify_content: JustifyContent::Center,
-            align_items: AlignItems::Center,
-            ..default()
-        };
-        let button_icon_style = Style {
-            width: Val::Px(30.0),
-            position_type: PositionType::Absolute,
-            left: Val::Px(10.0),
-            ..default()
-        };
-        let button_text_style = TextStyle {
-            font_size: 40.0,
-            color: WHITE.into(),
-            ..default()
-        };
+    fn high_scores_setup(
+        mut commands: Commands,
+        save_data: Res<SaveData>,
+        mode: Res<GameMode>,
+        difficulty: Res<Difficulty>,
+        game_assets: Res<GameAssets>,
+    ) {
+        let table = save_data.high_score_table(*mode, *difficulty);
 
         commands
             .spawn((
@@ -716,7 +6598,7 @@
                     },
                     ..default()
                 },
-                OnMainMenuScreen,
+                OnHighScoresScreen,
             ))
             .with_children(|parent| {
                 parent
@@ -724,105 +6606,442 @@
                         style: Style {
                             flex_direction: FlexDirection::Column,
                             align_items: AlignItems::Center,
+                            padding: UiRect::all(Val::Px(30.0)),
                             ..default()
                         },
                         background_color: CRIMSON.into(),
                         ..default()
                     })
                     .with_children(|parent| {
-                        // Display the game name
+                        parent.spawn(TextBundle::from_section(
+                            "High Scores",
+                            game_assets.text_style(60.0, WHITE.into()),
+                        ));
                         parent.spawn(
                             TextBundle::from_section(
-                                "Game Over",
-                                TextStyle {
-                                    font_size: 80.0,
-                                    color: WHITE.into(),
-                                    ..default()
-                                },
+                                format!("{} - {}", mode.label(), difficulty.label()),
+                                game_assets.hud_text_style(HUD_LABEL_COLOR),
                             )
                                 .with_style(Style {
-                                    margin: UiRect::all(Val::Px(50.0)),
+                                    margin: UiRect::bottom(Val::Px(20.0)),
                                     ..default()
                                 }),
                         );
 
-                        parent
-                            .spawn((
-                                ButtonBundle {
-                                    style: button_style.clone(),
-                                    background_color: NORMAL_BUTTON.into(),
-                                    ..default()
-                                },
-                                MenuButtonAction::Play,
-                            ))
-                            .with_children(|parent| {
-                                parent.spawn(ImageBundle {
-                                    style: button_icon_style.clone(),
-                                    ..default()
-                                });
-                                parent.spawn(TextBundle::from_section(
-                                    "New Game",
-                                    button_text_style.clone(),
-                                ));
-                            });
+                        let rows = if table.is_empty() {
+                            "NO SCORES YET".to_string()
+                        } else {
+                            table
+                                .iter()
+                                .enumerate()
+                                .map(|(rank, entry)| match *mode {
+                                    GameMode::Campaign => format!("{:>2}. {} {} {}", rank + 1, entry.name, format_score(entry.score), format_date(entry.date)),
+                                    GameMode::Endless => format!("{:>2}. {} {} {} {}", rank + 1, entry.name, format_score(entry.score), format_duration(entry.survived), format_date(entry.date)),
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        };
+                        parent.spawn(TextBundle::from_section(rows, game_assets.hud_text_style(WHITE.into())));
+
+                        spawn_menu_button(parent, &game_assets, "Back", MenuButtonAction::BackToMainMenu);
                     });
             });
     }
 
-    fn menu_action(
-        interaction_query: Query<(&Interaction, &MenuButtonAction), (Changed<Interaction>, With<Button>)>,
-        mut app_exit_events: EventWriter<AppExit>,
-        mut menu_state: ResMut<NextState<MenuState>>,
-        mut game_state: ResMut<NextState<GameState>>,
-        mut commands: Commands,
-    ) {
-        for (interaction, menu_button_action) in &interaction_query {
-            if *interaction == Interaction::Pressed {
-
-                match menu_button_action {
-                    MenuButtonAction::Quit => {
-                        app_exit_events.send(AppExit::Success);
-                    }
-                    MenuButtonAction::Play => {
-                        game_state.set(GameState::Game);
-                        menu_state.set(MenuState::Disabled);
-                    }
-                    MenuButtonAction::Settings => menu_state.set(MenuState::Settings),
-                    MenuButtonAction::SettingsDisplay => {
-                        menu_state.set(MenuState::SettingsDisplay);
-                    }
-                    MenuButtonAction::SettingsSound => {
-                        menu_state.set(MenuState::SettingsSound);
-                    }
-                    MenuButtonAction::BackToMainMenu => menu_state.set(MenuState::Main),
-                    MenuButtonAction::BackToSettings => {
-                        menu_state.set(MenuState::Settings);
-                    }
-                }
-            }
-        }
-    }
-
     fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
         for entity in &to_despawn {
             commands.entity(entity).despawn_recursive();
         }
     }
 
+    /// What a finished run leaves flying about besides its enemies.
+    type Leftover = Or<(With<Laser>, With<EnemyBullet>, With<PowerUp>, With<Formation>, With<Particle>)>;
+
     fn clear_after_game_over(
         mut commands: Commands,
         mut query: Query<Entity, With<SpawnTimer>>,
         mut enemy_query: Query<Entity, With<Enemy>>,
-        mut score_counter_query: Query<Entity, With<ScoreCounter>>,
+        laser_query: Query<Entity, Leftover>,
     ) {
-        for (spawn_timer) in &mut query {
+        // the next run keeps its own, the file stays for the menu's continue
+        commands.remove_resource::<Checkpoint>();
+        for spawn_timer in &mut query {
                 commands.entity(spawn_timer).despawn();
         }
-        for (enemy) in &mut enemy_query {
+        for enemy in &mut enemy_query {
             commands.entity(enemy).despawn();
         }
-        for (score_counter) in &mut score_counter_query {
-            commands.entity(score_counter).despawn();
+        for laser in &laser_query {
+            commands.entity(laser).despawn();
+        }
     }
+ 
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use bevy::state::app::StatesPlugin;
+
+        // a headless app with the game's own manifests loaded and a two player run set up
+        fn simulation_app() -> App {
+            let mut app = App::new();
+            app.add_plugins((MinimalPlugins, AssetPlugin::default(), StatesPlugin, TransformPlugin))
+                // restores rebuild sprites from these, the sprite plugin isn't there to register it
+                .register_type::<Sprite>()
+                .init_asset::<Image>()
+                .init_asset::<TextureAtlasLayout>()
+                .init_asset::<SpriteManifest>()
+                .init_asset_loader::<SpriteManifestLoader>()
+                .init_asset::<WeaponManifest>()
+                .init_asset_loader::<WeaponManifestLoader>()
+                .init_asset::<WaveManifest>()
+                .init_asset_loader::<WaveManifestLoader>()
+                .init_state::<GameState>()
+                .init_resource::<Playfield>()
+                .init_resource::<GameMode>()
+                .init_resource::<Difficulty>()
+                .init_resource::<SaveData>()
+                .insert_resource(CoopSettings { players: PlayerCount::Two, lives: LifeSharing::Separate })
+                .add_plugins(simulation_plugin);
+
+            let asset_server = app.world().resource::<AssetServer>().clone();
+            let game_assets = GameAssets {
+                sprites: asset_server.load("manifest.sprites.ron"),
+                weapons: asset_server.load("player.weapons.ron"),
+                waves: asset_server.load("campaign.waves.ron"),
+                // no text is drawn here
+                font: Handle::default(),
+            };
+            let ids = [game_assets.sprites.id().untyped(), game_assets.weapons.id().untyped(), game_assets.waves.id().untyped()];
+            app.insert_resource(game_assets);
+            // textures have no loader here, the manifests load regardless
+            for _ in 0..500 {
+                if ids.iter().all(|&id| matches!(asset_server.get_load_state(id), Some(LoadState::Loaded))) {
+                    break;
+                }
+                app.update();
+                std::thread::sleep(Duration::from_millis(10));
+            }
+
+            app.world_mut().run_system_once(setup);
+            app.insert_resource(GameRng(ChaCha8Rng::seed_from_u64(7)));
+            app
+        }
+
+        // both players weaving, firing in bursts and bombing now and then
+        fn scripted_input(tick: u32) -> Vec<InputFrame> {
+            (0..2)
+                .map(|player| InputFrame {
+                    x: [-100, 0, 100, 0][((tick / 40 + player * 2) % 4) as usize],
+                    y: [0, 60, 0, -60][((tick / 70 + player) % 4) as usize],
+                    shoot: !(tick / 25 + player).is_multiple_of(4),
+                    bomb: tick % 400 == 150 + player * 100,
+                })
+                .collect()
+        }
+
+        // a wrong guess at the same ticks, as a remote player's input would be predicted
+        fn mispredicted_input(tick: u32) -> Vec<InputFrame> {
+            scripted_input(tick)
+                .into_iter()
+                .map(|input| InputFrame { x: -input.x, shoot: !input.shoot, bomb: tick.is_multiple_of(7), ..input })
+                .collect()
+        }
+
+        fn run_tick(world: &mut World, input: Vec<InputFrame>) {
+            world.resource_mut::<TickInput>().0 = input;
+            world.resource_mut::<Time>().advance_by(Duration::from_secs_f64(1.0 / DEFAULT_TICK_RATE));
+            world.run_schedule(Simulation);
+        }
+
+        // the simulation's state in the order queries visit it, kind by kind since each kind
+        // has a table of its own. Sparse components are looked up one by one, a query asking
+        // for them would go by archetype instead of table order
+        fn fingerprint(world: &mut World) -> String {
+            let mut state = String::new();
+            let mut players = world.query::<(EntityRef, &PlayerId, &PhysicalTranslation, &Weapon)>();
+            for (entity, id, translation, weapon) in players.iter(world) {
+                state += &format!("{:?} {} {:?} {} {};", id, translation.0, weapon.kind, weapon.level, entity.contains::<Invulnerable>());
+            }
+            let mut enemies = world.query::<(EntityRef, &PhysicalTranslation, &Enemy)>();
+            for (entity, translation, enemy) in enemies.iter(world) {
+                state += &format!("{} {} {};", translation.0, enemy.health, entity.contains::<FormationMember>());
+            }
+            let mut lasers = world.query::<(&PhysicalTranslation, &Laser)>();
+            for (translation, laser) in lasers.iter(world) {
+                state += &format!("{} {} {};", translation.0, laser.damage, laser.hits.len());
+            }
+            let mut others = world.query_filtered::<&PhysicalTranslation, Or<(With<EnemyBullet>, With<PowerUp>, With<Formation>)>>();
+            for translation in others.iter(world) {
+                state += &format!("{};", translation.0);
+            }
+            let pilots: Vec<(u32, u32, u64)> = world.resource::<Pilots>().0.iter().map(|pilot| (pilot.lives, pilot.bombs, pilot.score)).collect();
+            state += &format!(
+                "score {} pilots {:?} wave {} rng {}",
+                world.resource::<Score>().points,
+                pilots,
+                world.resource::<Wave>().number,
+                world.resource::<GameRng>().get_word_pos()
+            );
+            state
+        }
+
+        #[test]
+        fn snapshot_counts_its_players() {
+            let mut app = simulation_app();
+            let world = app.world_mut();
+            let snapshot = world.run_system_once(take_snapshot);
+            assert_eq!(snapshot.player_count(), Some(2));
+
+            let registry = world.resource::<AppTypeRegistry>().clone();
+            let contents = snapshot.serialize(&registry.read()).unwrap();
+            assert_eq!(Snapshot::deserialize(&contents, &registry.read()).unwrap().player_count(), Some(2));
+        }
+
+        #[test]
+        fn rollback_resimulates_the_same_ticks() {
+            const TICKS: u32 = 1500;
+            const ROLLBACK_EVERY: u32 = 45;
+            const ROLLBACK_TICKS: u32 = 8;
+
+            let mut straight = simulation_app();
+            let expected: Vec<String> = (0..TICKS)
+                .map(|tick| {
+                    run_tick(straight.world_mut(), scripted_input(tick));
+                    fingerprint(straight.world_mut())
+                })
+                .collect();
+
+            let mut rolled_back = simulation_app();
+            let world = rolled_back.world_mut();
+            let mut rollbacks = 0;
+            let mut tick = 0;
+            while tick < TICKS {
+                if tick % ROLLBACK_EVERY == ROLLBACK_EVERY - 1 && tick + ROLLBACK_TICKS < TICKS {
+                    let snapshot = world.run_system_once(take_snapshot);
+                    for guessed in tick..tick + ROLLBACK_TICKS {
+                        run_tick(world, mispredicted_input(guessed));
+                    }
+                    restore_snapshot(world, &snapshot);
+                    rollbacks += 1;
+                    for resimulated in tick..tick + ROLLBACK_TICKS {
+                        run_tick(world, scripted_input(resimulated));
+                        assert_eq!(fingerprint(world), expected[resimulated as usize], "tick {resimulated} after rollback {rollbacks}");
+                    }
+                    tick += ROLLBACK_TICKS;
+                    continue;
+                }
+                run_tick(world, scripted_input(tick));
+                assert_eq!(fingerprint(world), expected[tick as usize], "tick {tick}");
+                tick += 1;
+            }
+            assert!(expected.last().is_some_and(|state| state.contains("rng")) && rollbacks > 20);
+        }
+
+        // the sprite shown at the start and after each frame at 4 fps, as `execute_animations`
+        // plays it, none once a one-shot clip has finished
+        fn played_frames(frames: Vec<usize>, mode: PlaybackMode, steps: usize) -> Vec<Option<usize>> {
+            let mut config = AnimationConfig::new(frames, Vec::new(), 4, mode);
+            let mut shown = vec![Some(config.sprite_index())];
+            for _ in 0..steps {
+                config.frame_timer.tick(Duration::from_millis(250));
+                if config.frame_timer.just_finished() && !config.advance() {
+                    config.finished = true;
+                }
+                shown.push((!config.finished).then(|| config.sprite_index()));
+            }
+            shown
+        }
+
+        #[test]
+        fn animations_advance_by_their_playback_mode() {
+            assert_eq!(played_frames(vec![3, 4, 5], PlaybackMode::Loop, 5), [3, 4, 5, 3, 4, 5].map(Some));
+            assert_eq!(played_frames(vec![3, 4, 5], PlaybackMode::Once, 4), [Some(3), Some(4), Some(5), None, None]);
+            assert_eq!(played_frames(vec![3, 4, 5], PlaybackMode::PingPong, 7), [3, 4, 5, 4, 3, 4, 5, 4].map(Some));
+            // nowhere to turn back to
+            assert_eq!(played_frames(vec![7], PlaybackMode::PingPong, 3), [7, 7, 7, 7].map(Some));
+            assert_eq!(played_frames(vec![7], PlaybackMode::Loop, 2), [7, 7, 7].map(Some));
+        }
+
+        fn decay_chain(world: &mut World, seconds: f32) -> (u32, u32) {
+            world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(seconds));
+            world.run_system_once(chain_decay_system);
+            let chain = world.resource::<Chain>();
+            (chain.multiplier, chain.kills)
+        }
+
+        #[test]
+        fn chains_build_with_kills_and_decay_a_step_at_a_time() {
+            let mut chain = Chain::default();
+            let multipliers: Vec<u32> = (0..9).map(|_| chain.register_kill()).collect();
+            assert_eq!(multipliers, [1, 1, 1, 2, 2, 2, 2, 3, 3]);
+            for _ in 0..40 {
+                chain.register_kill();
+            }
+            assert_eq!(chain.multiplier, CHAIN_MAX_MULTIPLIER);
+
+            let mut world = World::new();
+            world.init_resource::<Time>();
+            world.insert_resource(chain);
+            assert_eq!(decay_chain(&mut world, CHAIN_DECAY_SECONDS - 0.5), (CHAIN_MAX_MULTIPLIER, 49));
+            // down a step, with the kills of one at the start of it
+            assert_eq!(decay_chain(&mut world, 0.5), (7, 24));
+            assert_eq!(world.resource_mut::<Chain>().register_kill(), 7);
+            assert_eq!(world.resource::<Chain>().kills, 25);
+            // a kill starts the wait over
+            assert_eq!(decay_chain(&mut world, CHAIN_DECAY_SECONDS - 0.5), (7, 25));
+            assert_eq!(decay_chain(&mut world, 0.5), (6, 20));
+
+            for _ in 0..10 {
+                decay_chain(&mut world, CHAIN_DECAY_SECONDS);
+            }
+            assert_eq!(decay_chain(&mut world, CHAIN_DECAY_SECONDS), (1, 0));
+        }
+
+        fn entry(name: &str, score: u64, difficulty: Difficulty) -> HighScoreEntry {
+            HighScoreEntry { name: name.to_string(), score, mode: GameMode::Campaign, difficulty, seed: 0, date: 0, survived: 0 }
+        }
+
+        #[test]
+        fn format_date_counts_leap_days() {
+            assert_eq!(format_date(0), "1970-01-01");
+            assert_eq!(format_date(946_684_799), "1999-12-31");
+            assert_eq!(format_date(951_782_400), "2000-02-29");
+            assert_eq!(format_date(1_709_251_199), "2024-02-29");
+            // not a leap year, a century not divisible by 400
+            assert_eq!(format_date(4_107_542_400), "2100-03-01");
+        }
+
+        #[test]
+        fn challenges_ramp_up_to_a_cap() {
+            let hard = Difficulty::Hard.challenge();
+            let multipliers = |challenge: Challenge| [challenge.enemy_health, challenge.enemy_speed, challenge.fire_rate, challenge.bullet_speed];
+            let cases = [(0, 1.0, 24.0), (5, 1.4, 17.0), (DIFFICULTY_RAMP_MAX_WAVES, 1.8, 13.0), (50, 1.8, 13.0)];
+            for (waves, ramp, spawn_interval) in cases {
+                let ramped = hard.ramped(waves);
+                for (ramped, base) in multipliers(ramped).into_iter().zip(multipliers(hard)) {
+                    assert!((ramped - base * ramp).abs() < 1e-4, "{ramped} after {waves} waves, expected {}", base * ramp);
+                }
+                assert_eq!(ramped.spawn_interval, spawn_interval, "after {waves} waves");
+                // what the difficulty adds and pays stays as it is
+                assert_eq!((ramped.formation_bonus, ramped.score_percent), (1, 150));
+            }
+        }
+
+        #[test]
+        fn endless_ramps_from_the_second_tier_and_the_campaign_after_its_waves() {
+            let cases = [
+                (GameMode::Campaign, 1, 0),
+                (GameMode::Campaign, 5, 0),
+                (GameMode::Campaign, 6, 1),
+                (GameMode::Campaign, 9, 4),
+                (GameMode::Endless, 1, 0),
+                (GameMode::Endless, 2, 1),
+                (GameMode::Endless, 9, 8),
+            ];
+            for (mode, wave, ramp) in cases {
+                assert_eq!(mode.ramp_waves(wave, 5), ramp, "{mode:?} wave {wave}");
+            }
+        }
+
+        fn pilots(lives: &[u32]) -> Pilots {
+            Pilots(lives.iter().map(|&lives| Pilot { lives, bombs: STARTING_BOMBS, score: 0 }).collect())
+        }
+
+        #[test]
+        fn shared_lives_spare_a_last_life_while_the_partner_has_one_to_give() {
+            let cases = [
+                (&[2, 3], LifeSharing::Shared, true, [1, 3]),
+                (&[1, 3], LifeSharing::Shared, true, [1, 2]),
+                (&[1, 3], LifeSharing::Separate, false, [0, 3]),
+                // a partner's last life isn't theirs to give
+                (&[1, 1], LifeSharing::Shared, false, [0, 1]),
+                (&[1, 0], LifeSharing::Shared, false, [0, 0]),
+            ];
+            for (lives, sharing, still_playing, after) in cases {
+                let mut pilots = pilots(lives);
+                assert_eq!(pilots.lose_life(PlayerId::One, sharing), still_playing, "{lives:?} {sharing:?}");
+                assert_eq!(pilots.0.iter().map(|pilot| pilot.lives).collect::<Vec<_>>(), after, "{lives:?} {sharing:?}");
+            }
+
+            // the second player is spared by the first just the same
+            let mut pilots = pilots(&[3, 1]);
+            assert!(pilots.lose_life(PlayerId::Two, LifeSharing::Shared));
+            assert_eq!((pilots.get(PlayerId::One).lives, pilots.get(PlayerId::Two).lives), (2, 1));
+        }
+
+        #[test]
+        fn paths_are_followed_across_their_pieces() {
+            // playfield units come out as hundreds of world units
+            let playfield = Playfield { rect: Rect::from_center_size(Vec2::ZERO, Vec2::splat(200.0)) };
+            let path = EnemyPath { points: vec![Vec2::new(0.0, 1.0), Vec2::ZERO, Vec2::new(0.5, 0.0), Vec2::new(0.5, -0.5)] };
+            let cases = [
+                (0.0, Vec2::new(0.0, 100.0)),
+                (40.0, Vec2::new(0.0, 60.0)),
+                (100.0, Vec2::ZERO),
+                (125.0, Vec2::new(25.0, 0.0)),
+                (150.0, Vec2::new(50.0, 0.0)),
+                (175.0, Vec2::new(50.0, -25.0)),
+                (200.0, Vec2::new(50.0, -50.0)),
+                // past the end it carries on the way the last piece was going
+                (230.0, Vec2::new(50.0, -80.0)),
+            ];
+            for (distance, expected) in cases {
+                let point = path.point_at(distance, &playfield);
+                assert!(point.distance(expected) < 1e-3, "{point} at {distance}, expected {expected}");
+            }
+        }
+
+        #[test]
+        fn v_formations_widen_by_a_rank_every_two_members() {
+            let cases = [
+                (0, Vec2::ZERO),
+                (1, Vec2::new(-10.0, 7.5)),
+                (2, Vec2::new(10.0, 7.5)),
+                (3, Vec2::new(-20.0, 15.0)),
+                (4, Vec2::new(20.0, 15.0)),
+                (5, Vec2::new(-30.0, 22.5)),
+            ];
+            for (index, expected) in cases {
+                assert_eq!(FormationShape::V.slot(index, 6, 10.0, 0.0), expected, "member {index}");
+            }
+        }
+
+        #[test]
+        fn high_scores_qualify_above_the_lowest_of_a_full_table() {
+            let mut save_data = SaveData::default();
+            assert!(!save_data.qualifies(GameMode::Campaign, Difficulty::Normal, 0));
+            assert!(save_data.qualifies(GameMode::Campaign, Difficulty::Normal, 1));
+
+            for score in 1..=HIGH_SCORE_TABLE_SIZE as u64 {
+                save_data.add_high_score(entry("AAA", score * 100, Difficulty::Normal));
+            }
+            assert!(!save_data.qualifies(GameMode::Campaign, Difficulty::Normal, 100));
+            assert!(save_data.qualifies(GameMode::Campaign, Difficulty::Normal, 101));
+            // every mode and difficulty has a table of its own
+            assert!(save_data.qualifies(GameMode::Campaign, Difficulty::Hard, 1));
+            assert!(save_data.qualifies(GameMode::Endless, Difficulty::Normal, 1));
+        }
+
+        #[test]
+        fn adding_a_high_score_keeps_the_top_of_its_table() {
+            let mut save_data = SaveData::default();
+            save_data.add_high_score(entry("HRD", 50, Difficulty::Hard));
+            for score in 1..=HIGH_SCORE_TABLE_SIZE as u64 {
+                save_data.add_high_score(entry("OLD", score * 100, Difficulty::Normal));
+            }
+            // ties with the lowest go to whoever got there first
+            save_data.add_high_score(entry("TIE", 100, Difficulty::Normal));
+            save_data.add_high_score(entry("NEW", 550, Difficulty::Normal));
+
+            let table: Vec<(&str, u64)> = save_data.high_score_table(GameMode::Campaign, Difficulty::Normal)
+                .iter()
+                .map(|entry| (entry.name.as_str(), entry.score))
+                .collect();
+            assert_eq!(table.len(), HIGH_SCORE_TABLE_SIZE);
+            assert_eq!(table[0], ("OLD", 1000));
+            assert_eq!(table[5], ("NEW", 550));
+            assert_eq!(table[HIGH_SCORE_TABLE_SIZE - 1], ("OLD", 200));
+            assert_eq!(save_data.high_score_table(GameMode::Campaign, Difficulty::Hard).len(), 1);
+            assert_eq!(save_data.high_scores.len(), HIGH_SCORE_TABLE_SIZE + 1);
+        }
     }
- 
\ No newline at end of file
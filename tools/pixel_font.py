@@ -0,0 +1,291 @@
+#!/usr/bin/env python3
+"""Writes assets/fonts/pixel-5x7.ttf, the HUD's pixel font, from the glyphs drawn below.
+
+Every glyph is 5 pixels wide in a 6 pixel cell. Capitals are 7 pixels tall on the baseline,
+with a row of headroom above them and two rows for descenders below, so a line is 10 pixels:
+at a font size of 20 each of its pixels is 2 by 2 on screen. Each run of lit pixels in a row
+becomes a rectangle in the outline, so the font renders as crisp squares at whole multiples.
+
+Run it again after editing a glyph: python3 tools/pixel_font.py
+"""
+
+import os
+import struct
+
+PIXEL = 128
+HEADROOM, CAP_HEIGHT, DESCENT = 1, 7, 2
+UNITS_PER_EM = (HEADROOM + CAP_HEIGHT + DESCENT) * PIXEL
+ADVANCE = 6 * PIXEL
+FAMILY = "Pixel 5x7"
+
+# rows from the top of the capitals down, rows past the seventh hang below the baseline
+GLYPHS = {
+    " ": [],
+    "!": ["..#..", "..#..", "..#..", "..#..", "..#..", ".....", "..#.."],
+    '"': [".#.#.", ".#.#."],
+    "#": [".#.#.", ".#.#.", "#####", ".#.#.", "#####", ".#.#.", ".#.#."],
+    "$": ["..#..", ".####", "#.#..", ".###.", "..#.#", "####.", "..#.."],
+    "%": ["##...", "##..#", "...#.", "..#..", ".#...", "#..##", "...##"],
+    "&": [".##..", "#..#.", "#.#..", ".#...", "#.#.#", "#..#.", ".##.#"],
+    "'": ["..#..", "..#..", ".#..."],
+    "(": ["...#.", "..#..", ".#...", ".#...", ".#...", "..#..", "...#."],
+    ")": [".#...", "..#..", "...#.", "...#.", "...#.", "..#..", ".#..."],
+    "*": [".....", "..#..", "#.#.#", ".###.", "#.#.#", "..#..", "....."],
+    "+": [".....", "..#..", "..#..", "#####", "..#..", "..#..", "....."],
+    ",": [".....", ".....", ".....", ".....", ".....", "..##.", "...#.", "..#.."],
+    "-": [".....", ".....", ".....", "#####", ".....", ".....", "....."],
+    ".": [".....", ".....", ".....", ".....", ".....", ".##..", ".##.."],
+    "/": [".....", "....#", "...#.", "..#..", ".#...", "#....", "....."],
+    "0": [".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###."],
+    "1": ["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###."],
+    "2": [".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####"],
+    "3": ["#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###."],
+    "4": ["...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#."],
+    "5": ["#####", "#....", "####.", "....#", "....#", "#...#", ".###."],
+    "6": ["..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###."],
+    "7": ["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#..."],
+    "8": [".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###."],
+    "9": [".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##.."],
+    ":": [".....", ".##..", ".##..", ".....", ".##..", ".##..", "....."],
+    ";": [".....", ".##..", ".##..", ".....", ".##..", "..#..", ".#..."],
+    "<": ["...#.", "..#..", ".#...", "#....", ".#...", "..#..", "...#."],
+    "=": [".....", ".....", "#####", ".....", "#####", ".....", "....."],
+    ">": [".#...", "..#..", "...#.", "....#", "...#.", "..#..", ".#..."],
+    "?": [".###.", "#...#", "....#", "...#.", "..#..", ".....", "..#.."],
+    "@": [".###.", "#...#", "....#", ".##.#", "#.#.#", "#.#.#", ".###."],
+    "A": [".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"],
+    "B": ["####.", "#...#", "#...#", "####.", "#...#", "#...#", "####."],
+    "C": [".###.", "#...#", "#....", "#....", "#....", "#...#", ".###."],
+    "D": ["###..", "#..#.", "#...#", "#...#", "#...#", "#..#.", "###.."],
+    "E": ["#####", "#....", "#....", "####.", "#....", "#....", "#####"],
+    "F": ["#####", "#....", "#....", "####.", "#....", "#....", "#...."],
+    "G": [".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".####"],
+    "H": ["#...#", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"],
+    "I": [".###.", "..#..", "..#..", "..#..", "..#..", "..#..", ".###."],
+    "J": ["..###", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##.."],
+    "K": ["#...#", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "#...#"],
+    "L": ["#....", "#....", "#....", "#....", "#....", "#....", "#####"],
+    "M": ["#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#"],
+    "N": ["#...#", "#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#"],
+    "O": [".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."],
+    "P": ["####.", "#...#", "#...#", "####.", "#....", "#....", "#...."],
+    "Q": [".###.", "#...#", "#...#", "#...#", "#.#.#", "#..#.", ".##.#"],
+    "R": ["####.", "#...#", "#...#", "####.", "#.#..", "#..#.", "#...#"],
+    "S": [".####", "#....", "#....", ".###.", "....#", "....#", "####."],
+    "T": ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."],
+    "U": ["#...#", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."],
+    "V": ["#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#.."],
+    "W": ["#...#", "#...#", "#...#", "#.#.#", "#.#.#", "#.#.#", ".#.#."],
+    "X": ["#...#", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "#...#"],
+    "Y": ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#..", "..#.."],
+    "Z": ["#####", "....#", "...#.", "..#..", ".#...", "#....", "#####"],
+    "[": [".###.", ".#...", ".#...", ".#...", ".#...", ".#...", ".###."],
+    "\\": [".....", "#....", ".#...", "..#..", "...#.", "....#", "....."],
+    "]": [".###.", "...#.", "...#.", "...#.", "...#.", "...#.", ".###."],
+    "^": ["..#..", ".#.#.", "#...#"],
+    "_": [".....", ".....", ".....", ".....", ".....", ".....", "#####"],
+    "`": [".#...", "..#..", "...#."],
+    "a": [".....", ".....", ".###.", "....#", ".####", "#...#", ".####"],
+    "b": ["#....", "#....", "#.##.", "##..#", "#...#", "#...#", "####."],
+    "c": [".....", ".....", ".###.", "#....", "#....", "#...#", ".###."],
+    "d": ["....#", "....#", ".##.#", "#..##", "#...#", "#...#", ".####"],
+    "e": [".....", ".....", ".###.", "#...#", "#####", "#....", ".###."],
+    "f": ["..##.", ".#..#", ".#...", "###..", ".#...", ".#...", ".#..."],
+    "g": [".....", ".....", ".####", "#...#", "#...#", "#...#", ".####", "....#", ".###."],
+    "h": ["#....", "#....", "#.##.", "##..#", "#...#", "#...#", "#...#"],
+    "i": ["..#..", ".....", ".##..", "..#..", "..#..", "..#..", ".###."],
+    "j": ["...#.", ".....", "..##.", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##.."],
+    "k": ["#....", "#....", "#..#.", "#.#..", "##...", "#.#..", "#..#."],
+    "l": [".##..", "..#..", "..#..", "..#..", "..#..", "..#..", ".###."],
+    "m": [".....", ".....", "##.#.", "#.#.#", "#.#.#", "#...#", "#...#"],
+    "n": [".....", ".....", "#.##.", "##..#", "#...#", "#...#", "#...#"],
+    "o": [".....", ".....", ".###.", "#...#", "#...#", "#...#", ".###."],
+    "p": [".....", ".....", "####.", "#...#", "#...#", "#...#", "####.", "#....", "#...."],
+    "q": [".....", ".....", ".####", "#...#", "#...#", "#...#", ".####", "....#", "....#"],
+    "r": [".....", ".....", "#.##.", "##..#", "#....", "#....", "#...."],
+    "s": [".....", ".....", ".###.", "#....", ".###.", "....#", "####."],
+    "t": [".#...", ".#...", "###..", ".#...", ".#...", ".#..#", "..##."],
+    "u": [".....", ".....", "#...#", "#...#", "#...#", "#..##", ".##.#"],
+    "v": [".....", ".....", "#...#", "#...#", "#...#", ".#.#.", "..#.."],
+    "w": [".....", ".....", "#...#", "#...#", "#.#.#", "#.#.#", ".#.#."],
+    "x": [".....", ".....", "#...#", ".#.#.", "..#..", ".#.#.", "#...#"],
+    "y": [".....", ".....", "#...#", "#...#", "#...#", "#...#", ".####", "....#", ".###."],
+    "z": [".....", ".....", "#####", "...#.", "..#..", ".#...", "#####"],
+    "{": ["...#.", "..#..", "..#..", ".#...", "..#..", "..#..", "...#."],
+    "|": ["..#..", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."],
+    "}": [".#...", "..#..", "..#..", "...#.", "..#..", "..#..", ".#..."],
+    "~": [".....", ".....", ".#...", "#.#.#", "...#.", ".....", "....."],
+}
+
+# shown for anything the font doesn't have
+NOTDEF = ["#####", "#...#", "#...#", "#...#", "#...#", "#...#", "#####"]
+
+
+def rectangles(rows):
+    """The glyph as rectangles in font units, one per run of lit pixels in a row."""
+    for row, line in enumerate(rows):
+        top = (CAP_HEIGHT - row) * PIXEL
+        column = 0
+        while column < len(line):
+            if line[column] != "#":
+                column += 1
+                continue
+            start = column
+            while column < len(line) and line[column] == "#":
+                column += 1
+            # one pixel in from the left of the cell, the sixth column is the gap
+            yield ((start + 1) * PIXEL, top - PIXEL, (column + 1) * PIXEL, top)
+
+
+def glyph_data(rows):
+    boxes = list(rectangles(rows))
+    if not boxes:
+        return b"", (0, 0, 0, 0), 0, 0
+    x_min = min(box[0] for box in boxes)
+    y_min = min(box[1] for box in boxes)
+    x_max = max(box[2] for box in boxes)
+    y_max = max(box[3] for box in boxes)
+
+    end_points, points = [], []
+    for x0, y0, x1, y1 in boxes:
+        # clockwise, as TrueType wants outer contours
+        points += [(x0, y0), (x0, y1), (x1, y1), (x1, y0)]
+        end_points.append(len(points) - 1)
+
+    data = struct.pack(">hhhhh", len(boxes), x_min, y_min, x_max, y_max)
+    data += struct.pack(">%dH" % len(end_points), *end_points)
+    data += struct.pack(">H", 0)
+    # every point on the curve, coordinates as 16 bit deltas
+    data += bytes([0x01] * len(points))
+    previous = 0
+    for x, _ in points:
+        data += struct.pack(">h", x - previous)
+        previous = x
+    previous = 0
+    for _, y in points:
+        data += struct.pack(">h", y - previous)
+        previous = y
+    return data, (x_min, y_min, x_max, y_max), len(points), len(boxes)
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def name_table():
+    names = {
+        1: FAMILY,
+        2: "Regular",
+        3: FAMILY + " Regular",
+        4: FAMILY + " Regular",
+        5: "Version 1.0",
+        6: FAMILY.replace(" ", "") + "-Regular",
+    }
+    records, strings = b"", b""
+    for name_id, value in names.items():
+        encoded = value.encode("utf-16-be")
+        records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    return struct.pack(">HHH", 0, len(names), 6 + len(records)) + records + strings
+
+
+def cmap_table(first, last):
+    # one segment for the characters drawn and the closing one format 4 needs
+    end_codes = [last, 0xFFFF]
+    start_codes = [first, 0xFFFF]
+    deltas = [(1 - first) & 0xFFFF, 1]
+    seg_count = len(end_codes)
+    search_range = 2 * 2 ** (seg_count.bit_length() - 1)
+    subtable = struct.pack(
+        ">HHHHHHH", 4, 0, 0, seg_count * 2, search_range,
+        (seg_count.bit_length() - 1), seg_count * 2 - search_range,
+    )
+    subtable += struct.pack(">%dH" % seg_count, *end_codes) + struct.pack(">H", 0)
+    subtable += struct.pack(">%dH" % seg_count, *start_codes)
+    subtable += struct.pack(">%dH" % seg_count, *deltas)
+    subtable += struct.pack(">%dH" % seg_count, *([0] * seg_count))
+    subtable = subtable[:2] + struct.pack(">H", len(subtable)) + subtable[4:]
+    return struct.pack(">HHHHI", 0, 1, 3, 1, 12) + subtable
+
+
+def build():
+    characters = [chr(code) for code in range(32, 127)]
+    missing = [character for character in characters if character not in GLYPHS]
+    assert not missing, "no glyph drawn for %r" % missing
+    for character, rows in GLYPHS.items():
+        assert len(rows) <= CAP_HEIGHT + DESCENT and all(len(row) == 5 for row in rows), character
+
+    glyf, loca, hmtx = b"", [], b""
+    bounds, max_points, max_contours = [], 0, 0
+    for rows in [NOTDEF] + [GLYPHS[character] for character in characters]:
+        data, box, points, contours = glyph_data(rows)
+        loca.append(len(glyf))
+        glyf += data + b"\0" * (-len(data) % 4)
+        hmtx += struct.pack(">Hh", ADVANCE, box[0])
+        if data:
+            bounds.append(box)
+        max_points, max_contours = max(max_points, points), max(max_contours, contours)
+    loca.append(len(glyf))
+    glyph_count = len(characters) + 1
+
+    x_min = min(box[0] for box in bounds)
+    y_min = min(box[1] for box in bounds)
+    x_max = max(box[2] for box in bounds)
+    y_max = max(box[3] for box in bounds)
+    ascender = (CAP_HEIGHT + HEADROOM) * PIXEL
+    descender = -DESCENT * PIXEL
+
+    tables = {
+        "head": struct.pack(
+            ">IIIIHHqqhhhhHHhhh", 0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0x000B, UNITS_PER_EM,
+            0, 0, x_min, y_min, x_max, y_max, 0, 8, 2, 1, 0,
+        ),
+        "hhea": struct.pack(
+            ">IhhhHhhhhhhhhhhhH", 0x00010000, ascender, descender, 0, ADVANCE,
+            x_min, ADVANCE - x_max, x_max, 1, 0, 0, 0, 0, 0, 0, 0, glyph_count,
+        ),
+        "maxp": struct.pack(
+            ">IHHHHHHHHHHHHHH", 0x00010000, glyph_count, max_points, max_contours,
+            0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0,
+        ),
+        "OS/2": struct.pack(
+            ">HhHHHhhhhhhhhhhh10sIIII4sHHHhhhHHIIhhHHH", 4, ADVANCE, 400, 5, 0,
+            3 * PIXEL, 3 * PIXEL, 0, PIXEL, 3 * PIXEL, 3 * PIXEL, 0, 3 * PIXEL, PIXEL, 3 * PIXEL,
+            0, b"\0" * 10, 1, 0, 0, 0, b"NONE",
+            # regular, and the typo metrics are the ones to use
+            0x40 | 0x80, 32, 126, ascender, descender, 0, ascender, -descender, 1, 0,
+            5 * PIXEL, CAP_HEIGHT * PIXEL, 0, 32, 1,
+        ),
+        "hmtx": hmtx,
+        "cmap": cmap_table(32, 126),
+        "loca": struct.pack(">%dI" % len(loca), *loca),
+        "glyf": glyf,
+        "name": name_table(),
+        "post": struct.pack(">IIhhIIIII", 0x00030000, 0, -PIXEL, PIXEL, 1, 0, 0, 0, 0),
+    }
+
+    tags = sorted(tables)
+    entry_selector = len(tags).bit_length() - 1
+    search_range = 16 * 2 ** entry_selector
+    header = struct.pack(">IHHHH", 0x00010000, len(tags), search_range, entry_selector, len(tags) * 16 - search_range)
+    offset = len(header) + 16 * len(tags)
+    directory, body = b"", b""
+    for tag in tags:
+        data = tables[tag]
+        directory += struct.pack(">4sIII", tag.encode(), checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+    font = bytearray(header + directory + body)
+
+    # the head table's adjustment makes the whole file sum to a magic number
+    head_offset = offset + sum(len(tables[tag]) + (-len(tables[tag]) % 4) for tag in tags[:tags.index("head")])
+    struct.pack_into(">I", font, head_offset + 8, (0xB1B0AFBA - checksum(bytes(font))) & 0xFFFFFFFF)
+    return bytes(font)
+
+
+if __name__ == "__main__":
+    path = os.path.join(os.path.dirname(__file__), "..", "assets", "fonts", "pixel-5x7.ttf")
+    os.makedirs(os.path.dirname(path), exist_ok=True)
+    with open(path, "wb") as file:
+        file.write(build())
+    print("wrote", os.path.normpath(path))